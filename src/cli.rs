@@ -0,0 +1,391 @@
+use std::path::{Path, PathBuf};
+
+use crate::export::Format;
+
+pub const USAGE: &str = "\
+Usage: declarative-models [OPTIONS] <INPUT>...
+       declarative-models check [OPTIONS] <INPUT>...
+
+Compile declarative YAML models into glTF.
+
+Commands:
+  check                    Validate the inputs and list every problem without writing output
+
+Arguments:
+  <INPUT>...               YAML files or directories containing YAML files
+
+Options:
+  -o, --out <PATH>         Output directory, or output file when compiling a single input
+                           [default: output]
+  -f, --format <FORMAT>    Output format: embedded, binary or separate [default: embedded,
+                           or inferred from the --out file extension]
+  -s, --set <NAME=EXPR>    Set a variable of the inputs to a number or an expression instead
+                           of its value in the file; can be given more than once
+      --sweep <NAME=LIST>  Compile every input once for each value of a variable, given as a
+                           list like 1,2,5 or a range like 1..20 or 0..1:0.25 with a step,
+                           adding -<NAME>-<VALUE> to the output name; can be given more than
+                           once to compile every combination
+  -w, --watch              Keep running and rebuild files in the inputs whenever they, or files
+                           they include, change
+  -v, --verbose            Print details about every generated file
+  -q, --quiet              Only print errors
+  -h, --help               Print this help text";
+
+#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Verbosity {
+  Quiet,
+  Normal,
+  Verbose,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Command {
+  Build,
+  Check,
+}
+
+#[derive(Clone, Debug)]
+pub struct Options {
+  pub command: Command,
+  pub inputs: Vec<PathBuf>,
+  pub out: Option<PathBuf>,
+  pub format: Option<Format>,
+  /// Variables set to an expression, as pairs of name and expression.
+  pub variables: Vec<(String, String)>,
+  /// Variables to compile every input once for each value of, as pairs of name and values.
+  pub sweeps: Vec<(String, Vec<String>)>,
+  pub verbosity: Verbosity,
+  pub watch: bool,
+  pub help: bool,
+}
+
+/// A single input file, the variables it is compiled with and the path, without extension, that
+/// its output is written to.
+#[derive(Clone, Debug)]
+pub struct Job {
+  pub input: PathBuf,
+  pub variables: Vec<(String, String)>,
+  pub output: PathBuf,
+  pub format: Format,
+}
+
+/// The files to compile, and the problems with inputs that keep some of them from being compiled.
+/// Each problem counts as a failed file.
+#[derive(Clone, Debug)]
+pub struct Jobs {
+  pub jobs: Vec<Job>,
+  pub errors: Vec<String>,
+}
+
+impl Options {
+  pub fn parse(args: impl IntoIterator<Item=String>) -> Result<Self, String> {
+    let mut options = Options {
+      command: Command::Build,
+      inputs: Vec::new(),
+      out: None,
+      format: None,
+      variables: Vec::new(),
+      sweeps: Vec::new(),
+      verbosity: Verbosity::Normal,
+      watch: false,
+      help: false,
+    };
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+      match arg.as_str() {
+        "-o" | "--out" => {
+          let value = args.next().ok_or_else(|| format!("Missing value for '{}'", arg))?;
+          options.out = Some(PathBuf::from(value));
+        }
+        "-f" | "--format" => {
+          let value = args.next().ok_or_else(|| format!("Missing value for '{}'", arg))?;
+          options.format = Some(value.parse()?);
+        }
+        "-s" | "--set" => {
+          let value = args.next().ok_or_else(|| format!("Missing value for '{}'", arg))?;
+          let (name, expression) = assignment(&arg, &value)?;
+          options.variables.push((name.to_string(), expression.to_string()));
+        }
+        "--sweep" => {
+          let value = args.next().ok_or_else(|| format!("Missing value for '{}'", arg))?;
+          let (name, values) = assignment(&arg, &value)?;
+          options.sweeps.push((name.to_string(), sweep_values(values)?));
+        }
+        "-w" | "--watch" => options.watch = true,
+        "-v" | "--verbose" => options.verbosity = Verbosity::Verbose,
+        "-q" | "--quiet" => options.verbosity = Verbosity::Quiet,
+        "-h" | "--help" => options.help = true,
+        _ if arg.starts_with('-') && arg.len() > 1 => {
+          return Err(format!("Unknown option '{}'", arg));
+        }
+        "check" if options.command == Command::Build && options.inputs.is_empty() => {
+          options.command = Command::Check;
+        }
+        _ => options.inputs.push(PathBuf::from(arg)),
+      }
+    }
+
+    if options.inputs.is_empty() && !options.help {
+      return Err("No input files or directories given".to_string());
+    }
+
+    Ok(options)
+  }
+
+  /// Expand the inputs into the list of files to compile and where to write each of them. Inputs
+  /// that cannot be read, and files whose output would overwrite that of another file, are
+  /// reported as errors without stopping the other files.
+  pub fn jobs(&self) -> Result<Jobs, String> {
+    let mut errors = Vec::new();
+    let mut files = Vec::new();
+    for input in &self.inputs {
+      let found = if input.is_dir() {
+        let entries = match std::fs::read_dir(input) {
+          Ok(entries) => entries,
+          Err(err) => {
+            errors.push(format!("Cannot read directory '{}': {}", input.display(), err));
+            continue;
+          }
+        };
+
+        let mut dir_files: Vec<PathBuf> = entries
+          .filter_map(|it| it.ok())
+          .map(|it| it.path())
+          .filter(|it| it.is_file() && is_yaml(it))
+          .collect();
+        dir_files.sort();
+        dir_files
+      } else if input.is_file() {
+        vec![input.clone()]
+      } else {
+        errors.push(format!("Input '{}' does not exist", input.display()));
+        continue;
+      };
+
+      for file in found {
+        if !files.contains(&file) {
+          files.push(file);
+        }
+      }
+    }
+
+    let out = self.out.clone().unwrap_or_else(|| PathBuf::from("output"));
+    let out_format = out.extension()
+      .and_then(|it| it.to_str())
+      .and_then(|it| match it {
+        "gltf" => Some(Format::Embedded),
+        "glb" => Some(Format::Binary),
+        _ => None,
+      });
+
+    let jobs: Vec<Job> = if let Some(out_format) = out_format {
+      if files.len() > 1 || (files.is_empty() && errors.is_empty()) {
+        return Err(format!("Output '{}' is a file, but {} inputs were found", out.display(),
+          files.len()));
+      }
+
+      let format = self.format.unwrap_or(out_format);
+      let variants = self.variants();
+      files.into_iter()
+        .flat_map(|input| {
+          let base = out.with_extension("");
+          variants.iter().map(move |(variables, suffix)| {
+            let output = with_suffix(&base, suffix);
+            Job { input: input.clone(), variables: variables.clone(), output, format }
+          })
+        })
+        .collect()
+    } else {
+      let format = self.format.unwrap_or(Format::Embedded);
+      let variants = self.variants();
+      files.into_iter()
+        .flat_map(|input| {
+          let stem = out.join(input.file_stem().unwrap_or_default());
+          variants.iter().map(move |(variables, suffix)| {
+            let output = with_suffix(&stem, suffix);
+            Job { input: input.clone(), variables: variables.clone(), output, format }
+          })
+        })
+        .collect()
+    };
+
+    // Files with the same name in different directories would overwrite each other.
+    let mut unique: Vec<Job> = Vec::new();
+    for job in jobs {
+      match unique.iter().find(|it| it.output == job.output) {
+        Some(other) => errors.push(format!("Inputs '{}' and '{}' are both written to '{}'",
+          other.input.display(), job.input.display(), job.output.display())),
+        None => unique.push(job),
+      }
+    }
+
+    Ok(Jobs { jobs: unique, errors })
+  }
+
+  /// Every combination of the values of the sweeps, with the variables set, as the variables to
+  /// compile an input with and the suffix added to its output name.
+  fn variants(&self) -> Vec<(Vec<(String, String)>, String)> {
+    let mut variants = vec![(self.variables.clone(), String::new())];
+    for (name, values) in &self.sweeps {
+      variants = variants.into_iter()
+        .flat_map(|(variables, suffix)| {
+          values.iter().map(move |value| {
+            let mut variables = variables.clone();
+            variables.push((name.clone(), value.clone()));
+            let suffix = format!("{}-{}-{}", suffix, file_name_part(name), file_name_part(value));
+            (variables, suffix)
+          })
+        })
+        .collect();
+    }
+    variants
+  }
+}
+
+/// Split the value of an option like `--set` into a variable name and what follows the `=`.
+fn assignment<'a>(option: &str, value: &'a str) -> Result<(&'a str, &'a str), String> {
+  match value.split_once('=') {
+    Some((name, rest)) if !name.trim().is_empty() && !rest.trim().is_empty() => {
+      Ok((name.trim(), rest.trim()))
+    }
+    _ => Err(format!("Expected <NAME>=<VALUE> for '{}', found '{}'", option, value)),
+  }
+}
+
+/// The values of a sweep, from a list like `1,2,5` or a range like `1..20` or `0..1:0.25`.
+fn sweep_values(src: &str) -> Result<Vec<String>, String> {
+  let (range, step) = match src.split_once("..") {
+    Some((start, rest)) => match rest.split_once(':') {
+      Some((end, step)) => ((start, end), step),
+      None => ((start, rest), "1"),
+    },
+    None => return distinct_names(src, src.split(',').map(|it| it.trim().to_string()).collect()),
+  };
+
+  let number = |it: &str| it.trim().parse::<f64>()
+    .ok()
+    .filter(|it| it.is_finite())
+    .ok_or_else(|| format!("Invalid number '{}' in sweep '{}'", it.trim(), src));
+  let (start, end, step) = (number(range.0)?, number(range.1)?, number(step)?);
+  if step <= 0.0 {
+    return Err(format!("The step of sweep '{}' must be greater than 0", src));
+  }
+  if end < start {
+    return Err(format!("The range of sweep '{}' ends before it starts", src));
+  }
+
+  // Values are computed from the start rather than summed, and rounded so that steps like 0.1
+  // give short names.
+  let count = ((end - start) / step + 1e-9).floor() as usize + 1;
+  distinct_names(src, (0..count)
+    .map(|i| ((start + i as f64 * step) * 1e9).round() / 1e9)
+    .map(|it| it.to_string())
+    .collect())
+}
+
+/// Check that no two values of a sweep give the same output name.
+fn distinct_names(src: &str, values: Vec<String>) -> Result<Vec<String>, String> {
+  for (i, value) in values.iter().enumerate() {
+    if let Some(other) = values[..i].iter().find(|it| file_name_part(it) == file_name_part(value)) {
+      return Err(format!("Values '{}' and '{}' of sweep '{}' give the same output name", other,
+        value, src));
+    }
+  }
+  Ok(values)
+}
+
+/// Text that is safe to put in a file name, with every character other than ASCII letters,
+/// digits, `.`, `-` and `_` replaced by `_`.
+fn file_name_part(text: &str) -> String {
+  text.chars()
+    .map(|it| if it.is_ascii_alphanumeric() || matches!(it, '.' | '-' | '_') { it } else { '_' })
+    .collect()
+}
+
+/// A path with text added to the end of its file name.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+  let mut path = path.as_os_str().to_os_string();
+  path.push(suffix);
+  PathBuf::from(path)
+}
+
+fn is_yaml(path: &Path) -> bool {
+  matches!(path.extension().and_then(|it| it.to_str()), Some("yml") | Some("yaml"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn options(args: &[&str]) -> Result<Options, String> {
+    Options::parse(args.iter().map(|it| it.to_string()))
+  }
+
+  #[test]
+  fn ranges_include_their_end() {
+    assert_eq!(sweep_values("1..4").unwrap(), ["1", "2", "3", "4"]);
+    assert_eq!(sweep_values("2..2").unwrap(), ["2"]);
+    assert_eq!(sweep_values("-1..1:0.5").unwrap(), ["-1", "-0.5", "0", "0.5", "1"]);
+    // The end is only included when a step lands on it.
+    assert_eq!(sweep_values("0..1:0.3").unwrap(), ["0", "0.3", "0.6", "0.9"]);
+  }
+
+  #[test]
+  fn float_steps_do_not_accumulate_errors() {
+    let values = sweep_values("0..1:0.1").unwrap();
+    assert_eq!(values.len(), 11);
+    assert_eq!(values[3], "0.3");
+    assert_eq!(values[7], "0.7");
+    assert_eq!(values[10], "1");
+
+    let values = sweep_values("0..3:0.01").unwrap();
+    assert_eq!(values.len(), 301);
+    assert_eq!(values[300], "3");
+  }
+
+  #[test]
+  fn lists_are_kept_as_written() {
+    assert_eq!(sweep_values("1, 2,5").unwrap(), ["1", "2", "5"]);
+    assert_eq!(sweep_values("0.5,2*pi").unwrap(), ["0.5", "2*pi"]);
+  }
+
+  #[test]
+  fn invalid_ranges_are_errors() {
+    assert_eq!(sweep_values("0..1:0").unwrap_err(),
+      "The step of sweep '0..1:0' must be greater than 0");
+    assert_eq!(sweep_values("5..1").unwrap_err(),
+      "The range of sweep '5..1' ends before it starts");
+    assert_eq!(sweep_values("a..3").unwrap_err(), "Invalid number 'a' in sweep 'a..3'");
+    assert_eq!(sweep_values("0..inf").unwrap_err(), "Invalid number 'inf' in sweep '0..inf'");
+    assert_eq!(sweep_values("2*pi,2/pi").unwrap_err(),
+      "Values '2*pi' and '2/pi' of sweep '2*pi,2/pi' give the same output name");
+  }
+
+  #[test]
+  fn output_names_only_get_safe_characters() {
+    let options = options(&["--sweep", "r=1,2*pi,/x/(1 + 2),1e-3", "x.yml"]).unwrap();
+    let suffixes: Vec<String> = options.variants().into_iter().map(|(_, it)| it).collect();
+    assert_eq!(suffixes, ["-r-1", "-r-2_pi", "-r-_x__1___2_", "-r-1e-3"]);
+    assert_eq!(options.variants()[2].0, [("r".to_string(), "/x/(1 + 2)".to_string())]);
+
+    for suffix in &suffixes {
+      assert_eq!(with_suffix(Path::new("output/x"), suffix).parent(), Some(Path::new("output")));
+    }
+  }
+
+  #[test]
+  fn sweeps_compile_every_combination() {
+    let options = options(&["--set", "c=3", "--sweep", "a=1,2", "--sweep", "b=0..1:0.5", "x.yml"])
+      .unwrap();
+    let variants = options.variants();
+    let suffixes: Vec<&str> = variants.iter().map(|(_, suffix)| suffix.as_str()).collect();
+    assert_eq!(suffixes, [
+      "-a-1-b-0", "-a-1-b-0.5", "-a-1-b-1", "-a-2-b-0", "-a-2-b-0.5", "-a-2-b-1",
+    ]);
+    let variables: Vec<(&str, &str)> = variants[4].0.iter()
+      .map(|(name, value)| (name.as_str(), value.as_str()))
+      .collect();
+    assert_eq!(variables, [("c", "3"), ("a", "2"), ("b", "0.5")]);
+  }
+}
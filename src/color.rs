@@ -0,0 +1,342 @@
+use std::fmt;
+
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A color with linear red, green, blue and alpha channels from 0 to 1, as glTF expects them.
+///
+/// In YAML a color is written in sRGB as a hex string (`"#72532e"`, `"#72532eff"` or `"#fff"`), a
+/// named color (`brown`), a list of 0–255 integers (`[114, 83, 46]`) or a list of 0–1 numbers
+/// (`[0.45, 0.33, 0.18, 1.0]`). A list that contains any number with a decimal point is read as
+/// 0–1, otherwise as 0–255, so `[1, 1, 1]` is almost black and white is `[1.0, 1.0, 1.0]`.
+/// Wrapping any of these in `{ linear: ... }` takes the channels as linear instead of sRGB, and
+/// `{ srgb: ... }` states the default explicitly. Alpha is always linear and defaults to 1.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Color {
+  pub red: f32,
+  pub green: f32,
+  pub blue: f32,
+  pub alpha: f32,
+}
+
+impl Color {
+  pub const BLACK: Color = Color { red: 0.0, green: 0.0, blue: 0.0, alpha: 1.0 };
+  pub const WHITE: Color = Color { red: 1.0, green: 1.0, blue: 1.0, alpha: 1.0 };
+
+  pub fn linear(red: f32, green: f32, blue: f32, alpha: f32) -> Self {
+    Self { red, green, blue, alpha }
+  }
+
+  /// Convert sRGB channels from 0 to 1 to a linear color.
+  pub fn srgb(red: f32, green: f32, blue: f32, alpha: f32) -> Self {
+    Self {
+      red: srgb_to_linear(red),
+      green: srgb_to_linear(green),
+      blue: srgb_to_linear(blue),
+      alpha,
+    }
+  }
+
+  pub fn rgb(&self) -> [f32; 3] {
+    [self.red, self.green, self.blue]
+  }
+
+  pub fn rgba(&self) -> [f32; 4] {
+    [self.red, self.green, self.blue, self.alpha]
+  }
+
+  /// Parse a hex string or a color name as an sRGB color.
+  pub fn parse(s: &str) -> Option<Self> {
+    let [red, green, blue, alpha] = parse_channels(s)?;
+    Some(Self::srgb(red, green, blue, alpha))
+  }
+}
+
+impl Default for Color {
+  fn default() -> Self {
+    Color::WHITE
+  }
+}
+
+impl From<Color> for [f32; 4] {
+  fn from(color: Color) -> Self {
+    color.rgba()
+  }
+}
+
+fn srgb_to_linear(channel: f32) -> f32 {
+  if channel <= 0.04045 {
+    channel / 12.92
+  } else {
+    ((channel + 0.055) / 1.055).powf(2.4)
+  }
+}
+
+/// The channels of a hex string or a named color, from 0 to 1 and not yet converted to linear.
+fn parse_channels(s: &str) -> Option<[f32; 4]> {
+  let hex = match s.strip_prefix('#') {
+    Some(hex) => hex,
+    None => named_color(&s.to_ascii_lowercase())?,
+  };
+
+  let digit = |i: usize| u8::from_str_radix(hex.get(i..i + 1)?, 16).ok();
+  let byte = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+
+  let channels = match hex.len() {
+    3 => [digit(0)? * 17, digit(1)? * 17, digit(2)? * 17, 255],
+    4 => [digit(0)? * 17, digit(1)? * 17, digit(2)? * 17, digit(3)? * 17],
+    6 => [byte(0)?, byte(2)?, byte(4)?, 255],
+    8 => [byte(0)?, byte(2)?, byte(4)?, byte(6)?],
+    _ => return None,
+  };
+
+  Some(channels.map(|it| it as f32 / 255.0))
+}
+
+fn named_color(name: &str) -> Option<&'static str> {
+  Some(match name {
+    "black" => "000000",
+    "blue" => "0000ff",
+    "brown" => "a52a2a",
+    "cyan" => "00ffff",
+    "gold" => "ffd700",
+    "gray" | "grey" => "808080",
+    "green" => "008000",
+    "lime" => "00ff00",
+    "magenta" => "ff00ff",
+    "navy" => "000080",
+    "olive" => "808000",
+    "orange" => "ffa500",
+    "pink" => "ffc0cb",
+    "purple" => "800080",
+    "red" => "ff0000",
+    "silver" => "c0c0c0",
+    "tan" => "d2b48c",
+    "teal" => "008080",
+    "transparent" => "00000000",
+    "white" => "ffffff",
+    "yellow" => "ffff00",
+    _ => return None,
+  })
+}
+
+/// A number in a color list, which is read as 0–255 when it is an integer.
+enum Channel {
+  Integer(i64),
+  Float(f64),
+}
+
+impl<'de> Deserialize<'de> for Channel {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct ChannelVisitor;
+
+    impl<'de> Visitor<'de> for ChannelVisitor {
+      type Value = Channel;
+
+      fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a number")
+      }
+
+      fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(Channel::Integer(v))
+      }
+
+      fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Channel::Integer(v as i64))
+      }
+
+      fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(Channel::Float(v))
+      }
+    }
+
+    deserializer.deserialize_any(ChannelVisitor)
+  }
+}
+
+/// The channels of a color as written, from 0 to 1 and not yet converted to linear.
+struct RawColor([f32; 4]);
+
+impl<'de> Deserialize<'de> for RawColor {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    deserializer.deserialize_any(ColorVisitor { raw: true }).map(|it| RawColor(it.rgba()))
+  }
+}
+
+/// Reads a color in any of its forms. With `raw` set, the channels are returned as written, and
+/// cannot be wrapped in `{ linear: ... }` or `{ srgb: ... }`.
+struct ColorVisitor {
+  raw: bool,
+}
+
+impl ColorVisitor {
+  fn color(&self, [red, green, blue, alpha]: [f32; 4]) -> Color {
+    if self.raw {
+      Color { red, green, blue, alpha }
+    } else {
+      Color::srgb(red, green, blue, alpha)
+    }
+  }
+}
+
+impl<'de> Visitor<'de> for ColorVisitor {
+  type Value = Color;
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "a color as a hex string, a color name, a list of 3 or 4 numbers, or a map with a \
+      single `srgb` or `linear` key")
+  }
+
+  fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+    match parse_channels(v) {
+      Some(channels) => Ok(self.color(channels)),
+      None => Err(E::custom(format!("Unknown color '{}', expected #rgb, #rgba, #rrggbb, \
+        #rrggbbaa or a color name", v))),
+    }
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+    let mut values = Vec::new();
+    while let Some(value) = seq.next_element::<Channel>()? {
+      values.push(value);
+    }
+
+    if values.len() != 3 && values.len() != 4 {
+      return Err(de::Error::invalid_length(values.len(), &"3 or 4 channels"));
+    }
+
+    let is_float = values.iter().any(|it| matches!(it, Channel::Float(_)));
+    let mut channels = [1.0; 4];
+    for (channel, value) in channels.iter_mut().zip(&values) {
+      *channel = match *value {
+        Channel::Float(v) => v as f32,
+        Channel::Integer(v) if is_float => v as f32,
+        Channel::Integer(v) if (0..=255).contains(&v) => v as f32 / 255.0,
+        Channel::Integer(v) => {
+          return Err(de::Error::custom(format!("Color channel {} is not between 0 and 255", v)));
+        }
+      };
+
+      if !(0.0..=1.0).contains(channel) {
+        return Err(de::Error::custom(format!("Color channel {} is not between 0 and 1",
+          channel)));
+      }
+    }
+
+    Ok(self.color(channels))
+  }
+
+  fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+    if self.raw {
+      return Err(de::Error::custom("Color spaces cannot be nested"));
+    }
+
+    let key: String = map.next_key()?
+      .ok_or_else(|| de::Error::custom("Expected a `srgb` or `linear` key"))?;
+    let RawColor([red, green, blue, alpha]) = match key.as_str() {
+      "linear" | "srgb" => map.next_value()?,
+      _ => return Err(de::Error::unknown_field(&key, &["srgb", "linear"])),
+    };
+    if let Some(key) = map.next_key::<String>()? {
+      return Err(de::Error::custom(format!("Unexpected key `{}` after the color", key)));
+    }
+
+    Ok(if key == "linear" {
+      Color::linear(red, green, blue, alpha)
+    } else {
+      Color::srgb(red, green, blue, alpha)
+    })
+  }
+}
+
+impl<'de> Deserialize<'de> for Color {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    deserializer.deserialize_any(ColorVisitor { raw: false })
+  }
+}
+
+impl Serialize for Color {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(1))?;
+    map.serialize_entry("linear", &self.rgba())?;
+    map.end()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(src: &str) -> Color {
+    serde_yaml::from_str(src).unwrap()
+  }
+
+  fn assert_color_eq(actual: Color, expected: [f32; 4]) {
+    assert!(actual.rgba().iter().zip(&expected).all(|(a, b)| (a - b).abs() < 1e-5),
+      "expected {:?}, found {:?}", expected, actual.rgba());
+  }
+
+  #[test]
+  fn hex_strings_have_short_and_long_forms() {
+    let expected = Color::srgb(1.0, 0.2, 0.0, 1.0).rgba();
+    assert_color_eq(parse("'#f30'"), expected);
+    assert_color_eq(parse("'#f30f'"), expected);
+    assert_color_eq(parse("'#ff3300'"), expected);
+    assert_color_eq(parse("'#FF3300ff'"), expected);
+    assert_color_eq(parse("'#f308'"), Color::srgb(1.0, 0.2, 0.0, 136.0 / 255.0).rgba());
+
+    assert!(Color::parse("#ff33").is_some());
+    assert!(Color::parse("#ff330").is_none());
+    assert!(Color::parse("#ggg").is_none());
+  }
+
+  #[test]
+  fn names_are_case_insensitive() {
+    assert_color_eq(parse("red"), [1.0, 0.0, 0.0, 1.0]);
+    assert_color_eq(parse("White"), Color::WHITE.rgba());
+    assert_color_eq(parse("transparent"), [0.0, 0.0, 0.0, 0.0]);
+    assert_eq!(parse("brown"), parse("'#a52a2a'"));
+    assert!(serde_yaml::from_str::<Color>("mauve").is_err());
+  }
+
+  #[test]
+  fn integer_lists_are_0_to_255() {
+    assert_color_eq(parse("[255, 51, 0]"), Color::srgb(1.0, 0.2, 0.0, 1.0).rgba());
+    assert_color_eq(parse("[255, 255, 255, 0]"), [1.0, 1.0, 1.0, 0.0]);
+    assert!(serde_yaml::from_str::<Color>("[256, 0, 0]").is_err());
+    assert!(serde_yaml::from_str::<Color>("[-1, 0, 0]").is_err());
+
+    // Integers stay 0–255 even when every channel is 1, which is almost black.
+    let dark = 1.0 / 255.0;
+    assert_color_eq(parse("[1, 1, 1, 1]"), Color::srgb(dark, dark, dark, dark).rgba());
+    assert!(parse("[1, 1, 1]").red < 0.001);
+  }
+
+  #[test]
+  fn lists_with_a_decimal_point_are_0_to_1() {
+    assert_color_eq(parse("[1.0, 1.0, 1.0]"), Color::WHITE.rgba());
+    assert_color_eq(parse("[1, 0.2, 0]"), Color::srgb(1.0, 0.2, 0.0, 1.0).rgba());
+    assert_color_eq(parse("[0.5, 0.5, 0.5, 0.25]"), Color::srgb(0.5, 0.5, 0.5, 0.25).rgba());
+    assert!(serde_yaml::from_str::<Color>("[1.5, 0, 0]").is_err());
+    assert!(serde_yaml::from_str::<Color>("[1.0, 0]").is_err());
+  }
+
+  #[test]
+  fn color_spaces_can_be_given_explicitly() {
+    assert_color_eq(parse("{ linear: [0.5, 0.25, 0.0] }"), [0.5, 0.25, 0.0, 1.0]);
+    assert_color_eq(parse("{ linear: '#ff000080' }"), [1.0, 0.0, 0.0, 128.0 / 255.0]);
+    assert_eq!(parse("{ srgb: [0.5, 0.25, 0.0] }"), parse("[0.5, 0.25, 0.0]"));
+    assert_eq!(parse("{ srgb: teal }"), parse("teal"));
+
+    assert!(serde_yaml::from_str::<Color>("{ linear: { srgb: red } }").is_err());
+    assert!(serde_yaml::from_str::<Color>("{ rgb: red }").is_err());
+  }
+
+  #[test]
+  fn serialized_colors_read_back_the_same() {
+    let color = parse("'#72532e80'");
+    let yaml = serde_yaml::to_string(&color).unwrap();
+    assert_eq!(serde_yaml::from_str::<Color>(&yaml).unwrap(), color);
+  }
+}
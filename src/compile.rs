@@ -0,0 +1,1616 @@
+use std::collections::HashMap;
+
+use crate::{
+  geometry_buffer, gltf, AlphaMode, ArrayPattern, Camera, DocumentPath, Error, GenerateGeometry,
+  Geometry, GeometryBuffer, Interpolation, KeyframeValue, Light, MagFilter, MinFilter, Model, Node,
+  Primitive, ReferenceKind, Rotation, RotationOrder, Skin, Target, Texture, Vector3, Wrap, validate,
+};
+
+/// Compile a model into a glTF document, with all binary data stored in the buffers.
+///
+/// The model is validated first, and every problem found is reported at once.
+pub fn compile(model: &Model) -> Result<gltf::Gltf, Error> {
+  let errors = validate(model);
+  if !errors.is_empty() {
+    return Err(Error::from_errors(errors));
+  }
+
+  let mut model = model.clone();
+  apply_rotation_order(&mut model);
+  let model = expand_templates(&expand_arrays(&model));
+  let model = &model;
+
+  let mut buffer_data = Vec::new();
+
+  let mut accessors = Vec::new();
+  let mut animations = Vec::new();
+  let mut buffer_views = Vec::new();
+  let mut cameras = Vec::new();
+  let mut images = Vec::new();
+  let mut materials = Vec::new();
+  let mut meshes = Vec::new();
+  let mut lights = Vec::new();
+  let mut nodes = Vec::new();
+  let mut samplers = Vec::new();
+  let mut skins = Vec::new();
+  let mut textures = Vec::new();
+
+  let mut material_indices = HashMap::new();
+  let mut mesh_indices = HashMap::new();
+  let mut skinned_mesh_indices = HashMap::new();
+  let mut mesh_variants = HashMap::new();
+  let mut node_ids = HashMap::new();
+  let mut node_id_counter: u32 = 0;
+
+  // Skinned meshes and joints are placed relative to the root of the model.
+  let mut transforms = HashMap::new();
+  node_transforms(&model.nodes, glam::Mat4::IDENTITY, &mut transforms);
+  let mut mesh_skins = HashMap::new();
+  skinned_meshes(&model.nodes, &mut mesh_skins);
+
+  for (material_index, material) in model.materials.iter().enumerate() {
+    let mut push = |texture: &Option<Texture>| {
+      texture.as_ref().map(|it| push_texture(&mut images, &mut samplers, &mut textures, it))
+    };
+
+    let emissive_texture = push(&material.emissive_texture);
+    materials.push(gltf::Material {
+      name: material.name.clone(),
+      pbr_metallic_roughness: gltf::PBRMetallicRoughness {
+        base_color_factor: material.base_color.rgba(),
+        base_color_texture: push(&material.base_color_texture),
+        metallic_factor: material.metallic,
+        roughness_factor: material.roughness,
+        metallic_roughness_texture: push(&material.metallic_roughness_texture),
+      },
+      normal_texture: push(&material.normal_texture),
+      occlusion_texture: push(&material.occlusion_texture),
+      // The emissive texture is multiplied by the factor, which defaults to black.
+      emissive_factor: material.emissive.map(|it| it.rgb())
+        .or_else(|| emissive_texture.as_ref().map(|_| [1.0, 1.0, 1.0])),
+      emissive_texture,
+      alpha_mode: match material.alpha_mode {
+        AlphaMode::Opaque => None,
+        AlphaMode::Mask => Some(gltf::AlphaMode::Mask),
+        AlphaMode::Blend => Some(gltf::AlphaMode::Blend),
+      },
+      alpha_cutoff: material.alpha_cutoff,
+      double_sided: material.double_sided,
+    });
+
+    material_indices.insert(material.name.clone(), material_index as u32);
+  }
+
+  for (mesh_index, mesh) in model.meshes.iter().enumerate() {
+    let mut primitives = Vec::new();
+
+    // A mesh that only skinned nodes with the same skin use has the joints and weights itself.
+    // Otherwise every skin gets a copy of the mesh with its own, and the mesh stays unskinned.
+    let uses = mesh_skins.get(mesh.name.as_str()).map(Vec::as_slice).unwrap_or_default();
+    let shared_skin = matches!(uses, [Some(_)]);
+    let used_skins: Vec<&Skin> = uses.iter().flatten()
+      .filter_map(|name| model.skins.iter().find(|it| it.name == *name))
+      .collect();
+    let mut skin_attributes = vec![Vec::new(); used_skins.len()];
+
+    for (primitive_index, primitive) in mesh.primitives.iter().enumerate() {
+      let primitive_path = DocumentPath::root()
+        .key("meshes").index(mesh_index)
+        .key("primitives").index(primitive_index);
+
+      let buf = generate_buffer(&primitive.geometry, primitive_path.key("geometry"))?;
+
+      let flat = primitive.flat.unwrap_or(model.flat);
+      let (positions, normals, uvs) = if flat {
+        (buf.make_redundant(), buf.make_redundant_normals(), buf.make_redundant_uvs())
+      } else {
+        (buf.vertices.clone(), buf.smooth_normals(), buf.uvs.clone())
+      };
+      let colors = match (buf.colors.is_empty(), flat) {
+        (true, _) => None,
+        (false, true) => Some(buf.make_redundant_colors()),
+        (false, false) => Some(buf.colors.clone()),
+      };
+
+      let min = positions.iter().fold(Vector3::new(f32::MAX, f32::MAX, f32::MAX), |acc, it| acc.min(*it));
+      let max = positions.iter().fold(Vector3::new(f32::MIN, f32::MIN, f32::MIN), |acc, it| acc.max(*it));
+
+      let position = push_vertex_attribute(&mut buffer_data, &mut buffer_views, &mut accessors,
+        &positions, "VEC3", max.into(), min.into());
+      let normal = push_vertex_attribute(&mut buffer_data, &mut buffer_views, &mut accessors,
+        &normals, "VEC3", Vec::new(), Vec::new());
+      let texcoord = push_vertex_attribute(&mut buffer_data, &mut buffer_views, &mut accessors,
+        &uvs, "VEC2", Vec::new(), Vec::new());
+      let color = colors.map(|it| push_vertex_attribute(&mut buffer_data, &mut buffer_views,
+        &mut accessors, &it, "VEC4", Vec::new(), Vec::new()));
+
+      for (skin, attributes) in used_skins.iter().zip(&mut skin_attributes) {
+        let (joints, weights) = skin_vertices(skin, &transforms, primitive, &positions);
+        attributes.push((push_joints(&mut buffer_data, &mut buffer_views, &mut accessors, &joints),
+          push_vertex_attribute(&mut buffer_data, &mut buffer_views, &mut accessors,
+            &weights, "VEC4", Vec::new(), Vec::new())));
+      }
+      let (joints, weights) = match skin_attributes.first() {
+        Some(attributes) if shared_skin => (Some(attributes[primitive_index].0),
+          Some(attributes[primitive_index].1)),
+        _ => (None, None),
+      };
+
+      // Morph targets store the difference of every vertex from the primitive.
+      let mut targets = Vec::new();
+      for (target_index, target) in mesh.targets.iter().enumerate() {
+        let target_path = DocumentPath::root()
+          .key("meshes").index(mesh_index)
+          .key("targets").index(target_index);
+
+        let mut target_buf = match &target.primitives {
+          Some(target_primitives) => generate_buffer(&target_primitives[primitive_index].geometry,
+            target_path.key("primitives").index(primitive_index).key("geometry"))?,
+          None => buf.clone(),
+        };
+        if target_buf.vertices.len() != buf.vertices.len()
+          || target_buf.triangles.len() != buf.triangles.len() {
+          return Err(Error::geometry(target_path, format!(
+            "Morph target '{}' has {} vertices and {} triangles in primitive {}, but the primitive \
+              has {} vertices and {} triangles", target.name, target_buf.vertices.len(),
+            target_buf.triangles.len(), primitive_index, buf.vertices.len(), buf.triangles.len())));
+        }
+
+        if let Some(scale) = target.scale {
+          target_buf.scale(scale);
+        }
+        if let Some(offset) = target.offset {
+          target_buf.translate(offset);
+        }
+        if let Some(deform) = &target.deform {
+          target_buf.deform(deform.min, deform.max, Vector3::ONE, deform.seed);
+        }
+
+        let (target_positions, target_normals) = if flat {
+          (target_buf.make_redundant(), target_buf.make_redundant_normals())
+        } else {
+          (target_buf.vertices.clone(), target_buf.smooth_normals())
+        };
+        let position_deltas: Vec<Vector3> = target_positions.iter().zip(&positions)
+          .map(|(target, base)| *target - *base)
+          .collect();
+        let normal_deltas: Vec<Vector3> = target_normals.iter().zip(&normals)
+          .map(|(target, base)| *target - *base)
+          .collect();
+
+        let min = position_deltas.iter()
+          .fold(Vector3::new(f32::MAX, f32::MAX, f32::MAX), |acc, it| acc.min(*it));
+        let max = position_deltas.iter()
+          .fold(Vector3::new(f32::MIN, f32::MIN, f32::MIN), |acc, it| acc.max(*it));
+
+        targets.push(gltf::MorphTarget {
+          position: Some(push_vertex_attribute(&mut buffer_data, &mut buffer_views,
+            &mut accessors, &position_deltas, "VEC3", max.into(), min.into())),
+          normal: Some(push_vertex_attribute(&mut buffer_data, &mut buffer_views, &mut accessors,
+            &normal_deltas, "VEC3", Vec::new(), Vec::new())),
+        });
+      }
+
+      let indices = if flat {
+        None
+      } else {
+        Some(push_indices(&mut buffer_data, &mut buffer_views, &mut accessors,
+          &buf.triangles, positions.len()))
+      };
+
+      let material = primitive.material.as_ref()
+        .map(|it| material_indices.get(it).copied()
+          .ok_or_else(|| Error::reference(primitive_path.key("material"), ReferenceKind::Material, it)))
+        .transpose()?;
+
+      primitives.push(gltf::Primitive {
+        attributes: gltf::Attributes {
+          position: Some(position),
+          normal: Some(normal),
+          texcoord_0: Some(texcoord),
+          color_0: color,
+          joints_0: joints,
+          weights_0: weights,
+        },
+        indices,
+        material,
+        mode: 4,
+        targets,
+      });
+    }
+
+    let extras = if mesh.targets.is_empty() {
+      None
+    } else {
+      Some(gltf::MeshExtras {
+        target_names: mesh.targets.iter().map(|it| it.name.clone()).collect(),
+      })
+    };
+    meshes.push(gltf::Mesh {
+      primitives,
+      weights: mesh.targets.iter().map(|it| it.weight).collect(),
+      extras,
+    });
+
+    mesh_indices.insert(mesh.name.clone(), meshes.len() as u32 - 1);
+
+    if !shared_skin {
+      for (skin, attributes) in used_skins.iter().zip(skin_attributes) {
+        let mut variant = meshes[meshes.len() - 1].clone();
+        for (primitive, (joints, weights)) in variant.primitives.iter_mut().zip(attributes) {
+          primitive.attributes.joints_0 = Some(joints);
+          primitive.attributes.weights_0 = Some(weights);
+        }
+        meshes.push(variant);
+        skinned_mesh_indices.insert((mesh.name.as_str(), skin.name.as_str()),
+          meshes.len() as u32 - 1);
+      }
+    }
+  }
+
+  let mut node_stack = Vec::new();
+  let mut node_children = Vec::new();
+
+  for (i, node) in model.nodes.iter().enumerate() {
+    node_children.push((node, DocumentPath::root().key("nodes").index(i)));
+  }
+
+  while !node_children.is_empty() {
+    let (last, path) = node_children.remove(node_children.len() - 1);
+    for (i, child) in last.children.iter().enumerate() {
+      node_children.push((child, path.key("children").index(i)));
+    }
+    node_stack.push((last, path));
+  }
+
+  while !node_stack.is_empty() {
+    let (last, path) = node_stack.remove(node_stack.len() - 1);
+    node_ids.insert(last.name.clone(), node_id_counter);
+
+    let mut mesh = last.mesh.as_ref()
+      .map(|it| mesh_indices.get(it).copied()
+        .ok_or_else(|| Error::reference(path.key("mesh"), ReferenceKind::Mesh, it)))
+      .transpose()?;
+    // Skinned nodes use the copy of the mesh with the joints and weights of their skin, if any.
+    if let (Some(name), Some(skin)) = (&last.mesh, &last.skin) {
+      if let Some(index) = skinned_mesh_indices.get(&(name.as_str(), skin.as_str())) {
+        mesh = Some(*index);
+      }
+    }
+
+    // Nodes that replace the material share a copy of the mesh that uses the same accessors.
+    if let (Some(mesh_index), Some(material)) = (mesh, &last.material) {
+      let material = material_indices.get(material).copied()
+        .ok_or_else(|| Error::reference(path.key("material"), ReferenceKind::Material, material))?;
+      mesh = Some(*mesh_variants.entry((mesh_index, material)).or_insert_with(|| {
+        let mut variant: gltf::Mesh = meshes[mesh_index as usize].clone();
+        for primitive in &mut variant.primitives {
+          primitive.material = Some(material);
+        }
+        meshes.push(variant);
+        meshes.len() as u32 - 1
+      }));
+    }
+    let children: Vec<u32> = last.children.iter()
+      .map(|it| node_ids[&it.name])
+      .collect();
+
+    let rotation = last.rotation.map(|it| {
+      let quat = it.to_quat();
+      [quat.x, quat.y, quat.z, quat.w]
+    });
+
+    let skin = last.skin.as_ref()
+      .map(|it| model.skins.iter().position(|skin| skin.name == *it).map(|it| it as u32)
+        .ok_or_else(|| Error::reference(path.key("skin"), ReferenceKind::Skin, it)))
+      .transpose()?;
+
+    let camera = last.camera.as_ref().map(|it| {
+      cameras.push(compile_camera(it));
+      cameras.len() as u32 - 1
+    });
+    let extensions = last.light.as_ref().map(|it| {
+      lights.push(compile_light(it));
+      gltf::NodeExtensions {
+        lights_punctual: gltf::NodeLight { light: lights.len() as u32 - 1 },
+      }
+    });
+
+    nodes.push(gltf::Node {
+      mesh,
+      skin,
+      camera,
+      children,
+      translation: last.offset.map(|it| [it.x, it.y, it.z]),
+      rotation,
+      scale: last.scale.map(|it| [it.x, it.y, it.z]),
+      extensions,
+    });
+
+    node_id_counter += 1;
+  }
+
+  for (skin_index, skin) in model.skins.iter().enumerate() {
+    let path = DocumentPath::root().key("skins").index(skin_index).key("joints");
+    let mut joints = Vec::new();
+    let mut inverse_bind_matrices = Vec::new();
+    for (joint_index, joint) in skin.joints.iter().enumerate() {
+      let node = node_ids.get(joint)
+        .ok_or_else(|| Error::reference(path.index(joint_index), ReferenceKind::Node, joint))?;
+      joints.push(*node);
+      inverse_bind_matrices.extend(transforms[joint].inverse().to_cols_array());
+    }
+
+    let buffer_view = push_buffer_view(&mut buffer_data, &mut buffer_views,
+      bytemuck::cast_slice(&inverse_bind_matrices), None);
+    accessors.push(gltf::Accessor {
+      buffer_view,
+      byte_offset: 0,
+      component_type: 5126,
+      count: joints.len() as u32,
+      accessor_type: "MAT4".to_string(),
+      max: Vec::new(),
+      min: Vec::new(),
+    });
+
+    skins.push(gltf::Skin {
+      name: skin.name.clone(),
+      inverse_bind_matrices: accessors.len() as u32 - 1,
+      joints,
+    });
+  }
+
+  for (animation_index, animation) in model.animations.iter().enumerate() {
+    let mut gltf_animation = gltf::Animation {
+      name: animation.name.clone(),
+      ..Default::default()
+    };
+
+    for (channel_index, channel) in animation.channels.iter().enumerate() {
+      for (node_index, node_name) in channel.nodes.iter().enumerate() {
+        let node = *node_ids.get(node_name)
+          .ok_or_else(|| {
+            let path = DocumentPath::root()
+              .key("animations").index(animation_index)
+              .key("channels").index(channel_index)
+              .key("nodes").index(node_index);
+            Error::reference(path, ReferenceKind::Node, node_name)
+          })?;
+
+        // Validation only allows weights in weights channels and vectors in translation and
+        // scale channels.
+        let (times, values): (Vec<f32>, Vec<Vec<f32>>) = match channel.target {
+          Target::Rotation => rotation_keyframes(&channel.keyframes, channel.interpolation,
+            channel.order.unwrap_or(model.rotation_order)),
+          Target::Weights => channel.keyframes.iter()
+            .map(|(time, value)| (*time, value.weights().unwrap_or_default().to_vec()))
+            .unzip(),
+          Target::Translation | Target::Scale => channel.keyframes.iter()
+            .map(|(time, value)| (*time, Vec::from(value.vector().unwrap_or_default())))
+            .unzip(),
+        };
+
+        // Cubic spline outputs hold an in-tangent, a value and an out-tangent for every keyframe.
+        let outputs = match channel.interpolation {
+          Interpolation::CubicSpline => {
+            let tangents = match &channel.tangents {
+              Some(tangents) => tangents.iter()
+                .map(|[in_tangent, out_tangent]| (Vec::from(*in_tangent), Vec::from(*out_tangent)))
+                .collect(),
+              None => catmull_rom_tangents(&times, &values),
+            };
+            values.into_iter().zip(tangents)
+              .flat_map(|(value, (in_tangent, out_tangent))| vec![in_tangent, value, out_tangent])
+              .collect()
+          }
+          Interpolation::Linear | Interpolation::Step => values,
+        };
+
+        // Weights are written as one scalar per morph target for every output.
+        let (accessor_type, component_count) = match channel.target {
+          Target::Rotation => ("VEC4", 4),
+          Target::Weights => ("SCALAR", 1),
+          Target::Translation | Target::Scale => ("VEC3", 3),
+        };
+        let mut min_value = vec![f32::MAX; component_count];
+        let mut max_value = vec![f32::MIN; component_count];
+        for output in &outputs {
+          for (i, component) in output.iter().enumerate() {
+            min_value[i % component_count] = min_value[i % component_count].min(*component);
+            max_value[i % component_count] = max_value[i % component_count].max(*component);
+          }
+        }
+
+        let mut animation_data = times.clone();
+        let output_byte_offset = animation_data.len() as u32 * 4;
+        animation_data.extend(outputs.iter().flatten());
+
+        let buffer_view = push_buffer_view(&mut buffer_data, &mut buffer_views,
+          bytemuck::cast_slice(&animation_data), None);
+
+        let input_sampler = gltf::Accessor {
+          buffer_view,
+          byte_offset: 0,
+          component_type: 5126,
+          count: times.len() as u32,
+          accessor_type: "SCALAR".to_string(),
+          max: vec![times.iter().cloned().fold(f32::MIN, f32::max)],
+          min: vec![times.iter().cloned().fold(f32::MAX, f32::min)],
+        };
+
+        let output_sampler = gltf::Accessor {
+          buffer_view,
+          byte_offset: output_byte_offset,
+          component_type: 5126,
+          count: (outputs.iter().map(Vec::len).sum::<usize>() / component_count) as u32,
+          accessor_type: accessor_type.to_string(),
+          max: max_value,
+          min: min_value,
+        };
+
+        let input_sampler_id = accessors.len() as u32;
+        accessors.push(input_sampler);
+
+        let output_sampler_id = accessors.len() as u32;
+        accessors.push(output_sampler);
+
+        let path = match channel.target {
+          Target::Translation => gltf::Path::Translation,
+          Target::Rotation => gltf::Path::Rotation,
+          Target::Scale => gltf::Path::Scale,
+          Target::Weights => gltf::Path::Weights,
+        };
+
+        gltf_animation.channels.push(gltf::Channel {
+          sampler: gltf_animation.samplers.len() as u32,
+          target: gltf::Target { node, path },
+        });
+        gltf_animation.samplers.push(gltf::Sampler {
+          input: input_sampler_id,
+          output: output_sampler_id,
+          interpolation: match channel.interpolation {
+            Interpolation::Linear => gltf::Interpolation::Linear,
+            Interpolation::Step => gltf::Interpolation::Step,
+            Interpolation::CubicSpline => gltf::Interpolation::CubicSpline,
+          },
+        });
+      }
+    }
+
+    animations.push(gltf_animation);
+  }
+
+  let (extensions, extensions_used) = if lights.is_empty() {
+    (None, Vec::new())
+  } else {
+    let extensions = gltf::Extensions {
+      lights_punctual: Some(gltf::LightsPunctual { lights }),
+    };
+    (Some(extensions), vec!["KHR_lights_punctual".to_string()])
+  };
+
+  let scenes = if model.scenes.is_empty() {
+    vec![gltf::Scene {
+      name: None,
+      nodes: model.nodes.iter().map(|it| node_ids[&it.name]).collect(),
+    }]
+  } else {
+    let mut scenes = Vec::new();
+    for (scene_index, scene) in model.scenes.iter().enumerate() {
+      let path = DocumentPath::root().key("scenes").index(scene_index).key("nodes");
+      let nodes = scene.nodes.iter().enumerate()
+        .map(|(i, name)| node_ids.get(name).copied()
+          .ok_or_else(|| Error::reference(path.index(i), ReferenceKind::Node, name)))
+        .collect::<Result<_, _>>()?;
+      scenes.push(gltf::Scene { name: Some(scene.name.clone()), nodes });
+    }
+    scenes
+  };
+  let scene = match &model.default_scene {
+    Some(name) => {
+      let path = DocumentPath::root().key("defaultScene");
+      model.scenes.iter().position(|it| it.name == *name)
+        .ok_or_else(|| Error::reference(path, ReferenceKind::Scene, name))? as u32
+    }
+    None => 0,
+  };
+
+  // glTF does not allow empty buffers, so a model without any data has none.
+  let buffers = if buffer_data.is_empty() {
+    Vec::new()
+  } else {
+    vec![gltf::Buffer::new(buffer_data)]
+  };
+
+  let output = gltf::Gltf {
+    scene,
+    scenes,
+    nodes,
+    meshes,
+    buffers,
+    buffer_views,
+    accessors,
+    materials,
+    textures,
+    images,
+    samplers,
+    animations,
+    skins,
+    cameras,
+    extensions,
+    extensions_used,
+    asset: gltf::Asset { version: "2.0".to_string() },
+  };
+
+  Ok(output)
+}
+
+/// Generate and append the geometry entries of a primitive, where `path` is the path of the list
+/// of entries.
+fn generate_buffer(geometry: &[Geometry], path: DocumentPath) -> Result<GeometryBuffer, Error> {
+  let mut buf = GeometryBuffer::new();
+  for (geometry_index, geometry) in geometry.iter().enumerate() {
+    let generated = geometry.generate_geometry();
+    generated.validate()
+      .map_err(|message| Error::geometry(path.index(geometry_index), message))?;
+    buf.append(&generated);
+  }
+  Ok(buf)
+}
+
+fn compile_camera(camera: &Camera) -> gltf::Camera {
+  match camera {
+    Camera::Perspective(it) => gltf::Camera {
+      camera_type: gltf::CameraType::Perspective,
+      perspective: Some(gltf::PerspectiveCamera {
+        aspect_ratio: it.aspect_ratio,
+        yfov: it.yfov.to_radians(),
+        zfar: it.zfar,
+        znear: it.znear,
+      }),
+      orthographic: None,
+    },
+    Camera::Orthographic(it) => gltf::Camera {
+      camera_type: gltf::CameraType::Orthographic,
+      perspective: None,
+      orthographic: Some(gltf::OrthographicCamera {
+        xmag: it.xmag,
+        ymag: it.ymag,
+        zfar: it.zfar,
+        znear: it.znear,
+      }),
+    },
+  }
+}
+
+fn compile_light(light: &Light) -> gltf::Light {
+  match light {
+    Light::Point(it) => gltf::Light {
+      light_type: gltf::LightType::Point,
+      color: it.color.rgb(),
+      intensity: it.intensity,
+      range: it.range,
+      spot: None,
+    },
+    Light::Spot(it) => gltf::Light {
+      light_type: gltf::LightType::Spot,
+      color: it.color.rgb(),
+      intensity: it.intensity,
+      range: it.range,
+      spot: Some(gltf::Spot {
+        inner_cone_angle: it.inner_cone_angle.to_radians(),
+        outer_cone_angle: it.outer_cone_angle.to_radians(),
+      }),
+    },
+    Light::Directional(it) => gltf::Light {
+      light_type: gltf::LightType::Directional,
+      color: it.color.rgb(),
+      intensity: it.intensity,
+      range: None,
+      spot: None,
+    },
+  }
+}
+
+/// Collect the transform of every node relative to the root of the model, by name.
+fn node_transforms(
+  nodes: &[Node],
+  parent: glam::Mat4,
+  transforms: &mut HashMap<String, glam::Mat4>,
+) {
+  for node in nodes {
+    let scale = node.scale.unwrap_or(Vector3::ONE);
+    let offset = node.offset.unwrap_or_default();
+    let transform = parent * glam::Mat4::from_scale_rotation_translation(
+      glam::vec3(scale.x, scale.y, scale.z),
+      node.rotation.map(|it| it.to_quat()).unwrap_or(glam::Quat::IDENTITY),
+      glam::vec3(offset.x, offset.y, offset.z),
+    );
+    transforms.insert(node.name.clone(), transform);
+    node_transforms(&node.children, transform, transforms);
+  }
+}
+
+/// Collect the skins that the nodes using every mesh have, by mesh name, where `None` stands for
+/// nodes without a skin.
+fn skinned_meshes<'a>(nodes: &'a [Node], mesh_skins: &mut HashMap<&'a str, Vec<Option<&'a str>>>) {
+  for node in nodes {
+    if let Some(mesh) = &node.mesh {
+      let skins = mesh_skins.entry(mesh.as_str()).or_default();
+      let skin = node.skin.as_deref();
+      if !skins.contains(&skin) {
+        skins.push(skin);
+      }
+    }
+    skinned_meshes(&node.children, mesh_skins);
+  }
+}
+
+/// Choose the joints of a skin that every vertex of a primitive follows, as described on `Skin`,
+/// returning the joint indices and weights of every vertex.
+fn skin_vertices(
+  skin: &Skin,
+  transforms: &HashMap<String, glam::Mat4>,
+  primitive: &Primitive,
+  positions: &[Vector3],
+) -> (Vec<[u16; 4]>, Vec<[f32; 4]>) {
+  let joint_index = |name: &str| skin.joints.iter().position(|it| it == name).unwrap_or(0) as u16;
+  let joint_positions: Vec<Vector3> = skin.joints.iter()
+    .map(|it| transforms[it].transform_point3(glam::Vec3::ZERO).into())
+    .collect();
+
+  positions.iter()
+    .map(|position| {
+      if let Some(joint) = &primitive.joint {
+        return ([joint_index(joint), 0, 0, 0], [1.0, 0.0, 0.0, 0.0]);
+      }
+
+      let volumes: Vec<u16> = skin.volumes.iter()
+        .filter(|it| it.contains(*position))
+        .map(|it| joint_index(&it.joint))
+        .take(4)
+        .collect();
+      if !volumes.is_empty() {
+        let mut joints = [0; 4];
+        let mut weights = [0.0; 4];
+        for (i, joint) in volumes.iter().enumerate() {
+          joints[i] = *joint;
+          weights[i] = 1.0 / volumes.len() as f32;
+        }
+        return (joints, weights);
+      }
+
+      let nearest = joint_positions.iter()
+        .map(|it| it.distance(*position))
+        .enumerate()
+        .fold((0, f32::MAX), |nearest, (i, distance)| {
+          if distance < nearest.1 { (i, distance) } else { nearest }
+        })
+        .0;
+      ([nearest as u16, 0, 0, 0], [1.0, 0.0, 0.0, 0.0])
+    })
+    .unzip()
+}
+
+/// Replace every instance of a template with a copy of the template, as described on
+/// `Node::template`. Validation makes sure that no template instances itself.
+pub(crate) fn expand_templates(model: &Model) -> Model {
+  fn expand(node: &Node, templates: &[Node]) -> Node {
+    let mut expanded = node.clone();
+    expanded.children = node.children.iter().map(|it| expand(it, templates)).collect();
+
+    let template = node.template.as_ref()
+      .and_then(|name| templates.iter().find(|it| it.name == *name));
+    if let Some(template) = template {
+      let template = expand(template, templates);
+      expanded.mesh = expanded.mesh.or(template.mesh);
+      expanded.offset = expanded.offset.or(template.offset);
+      expanded.rotation = expanded.rotation.or(template.rotation);
+      expanded.scale = expanded.scale.or(template.scale);
+      expanded.skin = expanded.skin.or(template.skin);
+      expanded.camera = expanded.camera.or(template.camera);
+      expanded.light = expanded.light.or(template.light);
+      expanded.material = expanded.material.or(template.material);
+      expanded.children.extend(template.children.into_iter()
+        .map(|it| instance(it, &node.name, node.material.as_ref())));
+    }
+    expanded
+  }
+
+  fn instance(mut node: Node, instance_name: &str, material: Option<&String>) -> Node {
+    node.name = format!("{}/{}", instance_name, node.name);
+    if let Some(material) = material {
+      node.material = Some(material.clone());
+    }
+    node.children = node.children.into_iter()
+      .map(|it| instance(it, instance_name, material))
+      .collect();
+    node
+  }
+
+  let mut model = model.clone();
+  model.nodes = model.nodes.iter().map(|it| expand(it, &model.templates)).collect();
+  model
+}
+
+/// Replace every node with an array by its copies, as described on `Node::array`, in the nodes
+/// and in the children of templates. A copy keeps the transform of the node, with the transform of
+/// the copy applied after it and combined into a single offset, rotation and scale. Arrays are
+/// expanded before templates, so each copy of an instance gets its own copies of the children of
+/// the template.
+pub(crate) fn expand_arrays(model: &Model) -> Model {
+  fn expand(node: &Node) -> Vec<Node> {
+    let mut expanded = node.clone();
+    expanded.array = None;
+    expanded.children = node.children.iter().flat_map(expand).collect();
+
+    let pattern = match &node.array {
+      Some(pattern) => pattern,
+      None => return vec![expanded],
+    };
+    (0..pattern.count())
+      .map(|i| {
+        let (scale, rotation, offset) = pattern.transform(i);
+        let mut copy = expanded.clone();
+        copy.name = copy_name(&node.name, i);
+        copy.children = expanded.children.iter()
+          .map(|it| rename(it.clone(), &copy.name, i))
+          .collect();
+        let moved = scale * copy.offset.unwrap_or_default();
+        copy.offset = Some(offset + (rotation * glam::vec3(moved.x, moved.y, moved.z)).into());
+        if rotation != glam::Quat::IDENTITY {
+          let combined = rotation * copy.rotation.map_or(glam::Quat::IDENTITY, |it| it.to_quat());
+          copy.rotation = Some(Rotation::Quaternion {
+            x: combined.x, y: combined.y, z: combined.z, w: combined.w,
+          });
+        }
+        if <[f32; 3]>::from(scale) != [1.0; 3] {
+          copy.scale = Some(scale * copy.scale.unwrap_or(Vector3::ONE));
+        }
+        copy
+      })
+      .collect()
+  }
+
+  fn rename(mut node: Node, copy_name: &str, i: u32) -> Node {
+    node.name = if node.name.contains("{i}") {
+      node.name.replace("{i}", &i.to_string())
+    } else {
+      format!("{}/{}", copy_name, node.name)
+    };
+    node.children = node.children.into_iter().map(|it| rename(it, copy_name, i)).collect();
+    node
+  }
+
+  let mut model = model.clone();
+  model.nodes = model.nodes.iter().flat_map(expand).collect();
+  for template in &mut model.templates {
+    template.children = template.children.iter().flat_map(expand).collect();
+  }
+  model
+}
+
+/// The name of copy `i` of an array: `{i}` in the name is replaced by the index, which is
+/// appended when there is no `{i}`.
+pub(crate) fn copy_name(name: &str, i: u32) -> String {
+  if name.contains("{i}") {
+    name.replace("{i}", &i.to_string())
+  } else {
+    format!("{}{}", name, i)
+  }
+}
+
+/// Give every Euler angle rotation of a node or geometry entry without an order of its own the
+/// order of the model.
+pub(crate) fn apply_rotation_order(model: &mut Model) {
+  fn apply(rotation: &mut Option<Rotation>, default_order: RotationOrder) {
+    if let Some(Rotation::Euler { order, .. }) = rotation {
+      order.get_or_insert(default_order);
+    }
+  }
+
+  fn apply_array(pattern: &mut ArrayPattern, default_order: RotationOrder) {
+    if let ArrayPattern::Linear { rotation, .. } = pattern {
+      apply(rotation, default_order);
+    }
+  }
+
+  fn apply_geometry(geometry: &mut Geometry, default_order: RotationOrder) {
+    match geometry {
+      Geometry::Array(it) => {
+        apply_array(&mut it.pattern, default_order);
+        apply_geometry(&mut it.geometry, default_order);
+      }
+      Geometry::Cone(it) => apply(&mut it.rotation, default_order),
+      Geometry::Cube(it) => apply(&mut it.rotation, default_order),
+      Geometry::Cylinder(it) => apply(&mut it.rotation, default_order),
+      Geometry::Deform(it) => apply_geometry(&mut it.geometry, default_order),
+      Geometry::Icosphere(it) => apply(&mut it.rotation, default_order),
+      Geometry::Plane(it) => apply(&mut it.rotation, default_order),
+      Geometry::Triangle(it) => apply(&mut it.rotation, default_order),
+      Geometry::UvSphere(it) => apply(&mut it.rotation, default_order),
+    }
+  }
+
+  fn apply_node(node: &mut Node, default_order: RotationOrder) {
+    apply(&mut node.rotation, default_order);
+    if let Some(array) = &mut node.array {
+      apply_array(array, default_order);
+    }
+    for child in &mut node.children {
+      apply_node(child, default_order);
+    }
+  }
+
+  let default_order = model.rotation_order;
+  for mesh in &mut model.meshes {
+    for primitive in &mut mesh.primitives {
+      for geometry in &mut primitive.geometry {
+        apply_geometry(geometry, default_order);
+      }
+    }
+    let target_primitives = mesh.targets.iter_mut().flat_map(|it| it.primitives.iter_mut().flatten());
+    for target_primitive in target_primitives {
+      for geometry in &mut target_primitive.geometry {
+        apply_geometry(geometry, default_order);
+      }
+    }
+  }
+  for node in model.nodes.iter_mut().chain(&mut model.templates) {
+    apply_node(node, default_order);
+  }
+}
+
+/// Convert rotation keyframes to quaternions. Keyframes are inserted where a rotation between two
+/// keyframes reaches 180 degrees, as the shortest way between them would be ambiguous or turn the
+/// wrong way, and the signs of the quaternions are chosen so that each is in the same hemisphere
+/// as the one before it.
+fn rotation_keyframes(
+  keyframes: &[(f32, KeyframeValue)],
+  interpolation: Interpolation,
+  order: RotationOrder,
+) -> (Vec<f32>, Vec<Vec<f32>>) {
+  let mut expanded: Vec<(f32, KeyframeValue)> = keyframes.iter().take(1).cloned().collect();
+  for pair in keyframes.windows(2) {
+    let ((from_time, from), (to_time, to)) = (&pair[0], &pair[1]);
+    let steps = if interpolation == Interpolation::Step { 1 } else { rotation_steps(from, to) };
+    for step in 1..steps {
+      let t = step as f32 / steps as f32;
+      expanded.push((from_time + (to_time - from_time) * t, lerp_rotation(from, to, t)));
+    }
+    expanded.push(pair[1].clone());
+  }
+
+  let mut times = Vec::new();
+  let mut values = Vec::new();
+  let mut previous: Option<glam::Quat> = None;
+  for (time, value) in expanded {
+    let mut quat = value.to_quat(order);
+
+    if previous.is_some_and(|it| it.dot(quat) < 0.0) {
+      quat = -quat;
+    }
+    previous = Some(quat);
+
+    times.push(time);
+    values.push(vec![quat.x, quat.y, quat.z, quat.w]);
+  }
+
+  (times, values)
+}
+
+/// The number of steps to split the rotation between two keyframes into, so that every step turns
+/// less than 180 degrees. Keyframes written in different forms, or around different axes, cannot
+/// be split and always take one step.
+fn rotation_steps(from: &KeyframeValue, to: &KeyframeValue) -> u32 {
+  let degrees = match (from, to) {
+    (KeyframeValue::Vector(from), KeyframeValue::Vector(to)) => {
+      let delta = *to - *from;
+      delta.x.abs().max(delta.y.abs()).max(delta.z.abs())
+    }
+    (KeyframeValue::AxisAngle { axis: from_axis, angle: from },
+      KeyframeValue::AxisAngle { axis: to_axis, angle: to }) => {
+      if from_axis.normalize().distance(to_axis.normalize()) > 1e-6 {
+        return 1;
+      }
+      (to - from).abs()
+    }
+    _ => return 1,
+  };
+
+  (degrees / 180.0).floor() as u32 + 1
+}
+
+/// Interpolate between two rotation keyframes split by `rotation_steps`.
+fn lerp_rotation(from: &KeyframeValue, to: &KeyframeValue, t: f32) -> KeyframeValue {
+  match (from, to) {
+    (KeyframeValue::Vector(from), KeyframeValue::Vector(to)) => {
+      KeyframeValue::Vector(*from + (*to - *from) * Vector3::new(t, t, t))
+    }
+    (KeyframeValue::AxisAngle { axis, angle: from }, KeyframeValue::AxisAngle { angle: to, .. }) => {
+      KeyframeValue::AxisAngle { axis: *axis, angle: from + (to - from) * t }
+    }
+    _ => to.clone(),
+  }
+}
+
+/// Calculate Catmull-Rom style tangents for cubic spline keyframes, as the change of the value per
+/// second between the neighbouring keyframes. Returns the in-tangent and out-tangent of every
+/// keyframe, which are equal so that the curve is smooth.
+fn catmull_rom_tangents(times: &[f32], values: &[Vec<f32>]) -> Vec<(Vec<f32>, Vec<f32>)> {
+  (0..values.len())
+    .map(|i| {
+      let previous = i.saturating_sub(1);
+      let next = (i + 1).min(values.len() - 1);
+      let duration = times[next] - times[previous];
+
+      let tangent: Vec<f32> = values[next].iter().zip(&values[previous])
+        .map(|(next, previous)| if duration > 0.0 { (next - previous) / duration } else { 0.0 })
+        .collect();
+      (tangent.clone(), tangent)
+    })
+    .collect()
+}
+
+/// Add a texture, reusing any image, sampler and texture that is already identical, and return
+/// the reference to it for a material.
+fn push_texture(
+  images: &mut Vec<gltf::Image>,
+  samplers: &mut Vec<gltf::TextureSampler>,
+  textures: &mut Vec<gltf::Texture>,
+  texture: &Texture,
+) -> gltf::TextureInfo {
+  let source = match images.iter().position(|it| it.uri.as_deref() == Some(&texture.image)) {
+    Some(index) => index as u32,
+    None => {
+      images.push(gltf::Image {
+        uri: Some(texture.image.clone()),
+        mime_type: image_mime_type(&texture.image).map(|it| it.to_string()),
+        buffer_view: None,
+        data: None,
+      });
+      images.len() as u32 - 1
+    }
+  };
+
+  let wrap = |wrap: Wrap| match wrap {
+    Wrap::Repeat => 10497,
+    Wrap::ClampToEdge => 33071,
+    Wrap::MirroredRepeat => 33648,
+  };
+  let sampler = gltf::TextureSampler {
+    mag_filter: texture.mag_filter.map(|it| match it {
+      MagFilter::Nearest => 9728,
+      MagFilter::Linear => 9729,
+    }),
+    min_filter: texture.min_filter.map(|it| match it {
+      MinFilter::Nearest => 9728,
+      MinFilter::Linear => 9729,
+      MinFilter::NearestMipmapNearest => 9984,
+      MinFilter::LinearMipmapNearest => 9985,
+      MinFilter::NearestMipmapLinear => 9986,
+      MinFilter::LinearMipmapLinear => 9987,
+    }),
+    wrap_s: wrap(texture.wrap_s),
+    wrap_t: wrap(texture.wrap_t),
+  };
+  let sampler = match samplers.iter().position(|it| *it == sampler) {
+    Some(index) => index as u32,
+    None => {
+      samplers.push(sampler);
+      samplers.len() as u32 - 1
+    }
+  };
+
+  let index = match textures.iter().position(|it| it.source == source && it.sampler == sampler) {
+    Some(index) => index as u32,
+    None => {
+      textures.push(gltf::Texture { sampler, source });
+      textures.len() as u32 - 1
+    }
+  };
+
+  gltf::TextureInfo {
+    index,
+    tex_coord: 0,
+    scale: texture.scale,
+    strength: texture.strength,
+  }
+}
+
+/// The MIME type of an image supported by glTF, chosen by the extension of its path.
+pub(crate) fn image_mime_type(path: &str) -> Option<&'static str> {
+  let extension = std::path::Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+  match extension.as_str() {
+    "png" => Some("image/png"),
+    "jpg" | "jpeg" => Some("image/jpeg"),
+    _ => None,
+  }
+}
+
+/// Append a float vertex attribute such as `VEC3` positions or `VEC2` texture coordinates to the
+/// geometry buffer, returning the index of its accessor.
+fn push_vertex_attribute<T: bytemuck::Pod>(
+  buffer_data: &mut Vec<u8>,
+  buffer_views: &mut Vec<gltf::BufferView>,
+  accessors: &mut Vec<gltf::Accessor>,
+  data: &[T],
+  accessor_type: &str,
+  max: Vec<f32>,
+  min: Vec<f32>,
+) -> u32 {
+  let buffer_view = push_buffer_view(buffer_data, buffer_views,
+    bytemuck::cast_slice(data), Some(34962)); // vertices
+
+  accessors.push(gltf::Accessor {
+    buffer_view,
+    byte_offset: 0,
+    component_type: 5126,
+    count: data.len() as u32,
+    accessor_type: accessor_type.to_string(),
+    max,
+    min,
+  });
+
+  accessors.len() as u32 - 1
+}
+
+/// Append the `JOINTS_0` attribute of a skinned primitive to the geometry buffer, returning the
+/// index of its accessor.
+fn push_joints(
+  buffer_data: &mut Vec<u8>,
+  buffer_views: &mut Vec<gltf::BufferView>,
+  accessors: &mut Vec<gltf::Accessor>,
+  joints: &[[u16; 4]],
+) -> u32 {
+  let buffer_view = push_buffer_view(buffer_data, buffer_views,
+    bytemuck::cast_slice(joints), Some(34962)); // vertices
+
+  accessors.push(gltf::Accessor {
+    buffer_view,
+    byte_offset: 0,
+    component_type: 5123,
+    count: joints.len() as u32,
+    accessor_type: "VEC4".to_string(),
+    max: Vec::new(),
+    min: Vec::new(),
+  });
+
+  accessors.len() as u32 - 1
+}
+
+/// Append the triangle indices to the geometry buffer, returning the index of their accessor.
+/// Indices are written as `u16` when the vertex count allows it and as `u32` otherwise.
+fn push_indices(
+  buffer_data: &mut Vec<u8>,
+  buffer_views: &mut Vec<gltf::BufferView>,
+  accessors: &mut Vec<gltf::Accessor>,
+  triangles: &[geometry_buffer::Triangle],
+  vertex_count: usize,
+) -> u32 {
+  let indices: Vec<u32> = triangles.iter().flat_map(|it| it.0.iter().copied()).collect();
+
+  // The largest value of each component type is reserved for primitive restart.
+  let (buffer_view, component_type) = if vertex_count < u16::MAX as usize {
+    let short_indices: Vec<u16> = indices.iter().map(|it| *it as u16).collect();
+    (push_buffer_view(buffer_data, buffer_views, bytemuck::cast_slice(&short_indices),
+      Some(34963)), 5123) // indices
+  } else {
+    (push_buffer_view(buffer_data, buffer_views, bytemuck::cast_slice(&indices),
+      Some(34963)), 5125) // indices
+  };
+
+  accessors.push(gltf::Accessor {
+    buffer_view,
+    byte_offset: 0,
+    component_type,
+    count: indices.len() as u32,
+    accessor_type: "SCALAR".to_string(),
+    max: Vec::new(),
+    min: Vec::new(),
+  });
+
+  accessors.len() as u32 - 1
+}
+
+/// Append bytes to the geometry buffer in a new buffer view, returning the index of the view.
+fn push_buffer_view(
+  buffer_data: &mut Vec<u8>,
+  buffer_views: &mut Vec<gltf::BufferView>,
+  bytes: &[u8],
+  target: Option<u32>,
+) -> u32 {
+  while !buffer_data.len().is_multiple_of(4) {
+    buffer_data.push(0);
+  }
+
+  buffer_views.push(gltf::BufferView {
+    buffer: 0,
+    byte_offset: buffer_data.len() as u32,
+    byte_length: bytes.len() as u32,
+    target,
+  });
+  buffer_data.extend_from_slice(bytes);
+
+  buffer_views.len() as u32 - 1
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse_keyframes(src: &str) -> Vec<(f32, KeyframeValue)> {
+    serde_yaml::from_str(src).unwrap()
+  }
+
+  fn quat(value: &[f32]) -> glam::Quat {
+    glam::Quat::from_xyzw(value[0], value[1], value[2], value[3])
+  }
+
+  /// The angle in degrees between two rotations, the shorter way around.
+  fn angle_between(a: glam::Quat, b: glam::Quat) -> f32 {
+    2.0 * a.dot(b).abs().min(1.0).acos().to_degrees()
+  }
+
+  fn compile_src(src: &str) -> gltf::Gltf {
+    compile(&crate::parse(src).unwrap()).unwrap()
+  }
+
+  /// The bytes of an accessor in the buffer of a compiled model.
+  fn accessor_bytes(gltf: &gltf::Gltf, accessor: u32) -> &[u8] {
+    let accessor = &gltf.accessors[accessor as usize];
+    let view = &gltf.buffer_views[accessor.buffer_view as usize];
+    let components = match accessor.accessor_type.as_str() {
+      "VEC2" => 2,
+      "VEC3" => 3,
+      "VEC4" => 4,
+      "MAT4" => 16,
+      _ => 1,
+    };
+    let component_size = if accessor.component_type == 5123 { 2 } else { 4 };
+    let start = (view.byte_offset + accessor.byte_offset) as usize;
+    let length = accessor.count as usize * components * component_size;
+    &gltf.buffers[view.buffer as usize].data[start..start + length]
+  }
+
+  /// The values of an index accessor, which are 16 or 32 bits wide.
+  fn indices(gltf: &gltf::Gltf, accessor: u32) -> Vec<u32> {
+    let bytes = accessor_bytes(gltf, accessor);
+    match gltf.accessors[accessor as usize].component_type {
+      5123 => bytes.chunks(2).map(|it| u16::from_le_bytes([it[0], it[1]]) as u32).collect(),
+      _ => bytes.chunks(4).map(|it| u32::from_le_bytes([it[0], it[1], it[2], it[3]])).collect(),
+    }
+  }
+
+  /// The components of a float accessor.
+  fn floats(gltf: &gltf::Gltf, accessor: u32) -> Vec<f32> {
+    accessor_bytes(gltf, accessor).chunks(4)
+      .map(|it| f32::from_le_bytes([it[0], it[1], it[2], it[3]]))
+      .collect()
+  }
+
+  /// The vectors of a `VEC3` accessor.
+  fn vectors(gltf: &gltf::Gltf, accessor: u32) -> Vec<glam::Vec3> {
+    floats(gltf, accessor).chunks(3).map(|it| glam::vec3(it[0], it[1], it[2])).collect()
+  }
+
+  #[test]
+  fn turns_of_180_degrees_or_more_are_split() {
+    let keyframes = parse_keyframes("[[0, { x: 0 }], [3, { x: 360 }]]");
+    let (times, values) = rotation_keyframes(&keyframes, Interpolation::Linear, RotationOrder::Xyz);
+
+    assert_eq!(times, vec![0.0, 1.0, 2.0, 3.0]);
+    for (i, value) in values.iter().enumerate() {
+      let expected = glam::Quat::from_rotation_x((120.0 * i as f32).to_radians());
+      assert!(angle_between(quat(value), expected) < 1e-3);
+    }
+    for pair in values.windows(2) {
+      assert!(angle_between(quat(&pair[0]), quat(&pair[1])) < 180.0);
+    }
+
+    let keyframes =
+      parse_keyframes("[[0, { axis: { y: 1 }, angle: 0 }], [1, { axis: { y: 1 }, angle: -180 }]]");
+    let (times, _) = rotation_keyframes(&keyframes, Interpolation::Linear, RotationOrder::Xyz);
+    assert_eq!(times, vec![0.0, 0.5, 1.0]);
+  }
+
+  #[test]
+  fn turns_are_not_split_when_they_cannot_be_interpolated() {
+    let keyframes = parse_keyframes("[[0, { x: 0 }], [1, { x: 270 }]]");
+    let (times, _) = rotation_keyframes(&keyframes, Interpolation::Step, RotationOrder::Xyz);
+    assert_eq!(times, vec![0.0, 1.0]);
+
+    let keyframes =
+      parse_keyframes("[[0, { axis: { x: 1 }, angle: 0 }], [1, { axis: { y: 1 }, angle: 270 }]]");
+    let (times, _) = rotation_keyframes(&keyframes, Interpolation::Linear, RotationOrder::Xyz);
+    assert_eq!(times, vec![0.0, 1.0]);
+
+    let keyframes = parse_keyframes("[[0, { x: 10 }], [1, { x: 150 }]]");
+    let (times, _) = rotation_keyframes(&keyframes, Interpolation::Linear, RotationOrder::Xyz);
+    assert_eq!(times, vec![0.0, 1.0]);
+  }
+
+  #[test]
+  fn consecutive_quaternions_keep_the_same_sign() {
+    // The identity written as w = -1 is the opposite of the quaternion a turn of 90 degrees ends
+    // on, so it is flipped, and the identity after it follows.
+    let keyframes =
+      parse_keyframes("[[0, { z: 90 }], [1, { w: -1 }], [2, { x: 0 }], [3, { z: 120 }]]");
+    let (_, values) = rotation_keyframes(&keyframes, Interpolation::Linear, RotationOrder::Xyz);
+
+    assert_eq!(values.len(), 4);
+    assert_eq!(values[1], vec![0.0, 0.0, 0.0, 1.0]);
+    assert_eq!(values[2], vec![0.0, 0.0, 0.0, 1.0]);
+    for pair in values.windows(2) {
+      assert!(quat(&pair[0]).dot(quat(&pair[1])) >= 0.0, "{:?}", pair);
+    }
+    let last = glam::Quat::from_rotation_z(120f32.to_radians());
+    assert!(angle_between(quat(&values[3]), last) < 1e-3);
+  }
+
+  #[test]
+  fn only_skinned_nodes_get_skin_attributes() {
+    let model = crate::parse("
+meshes:
+  - name: box
+    primitives:
+      - geometry: [Cube: { size: { x: 1, y: 1, z: 1 } }]
+nodes:
+  - name: bone
+  - name: skinned
+    mesh: box
+    skin: rig
+  - name: plain
+    mesh: box
+skins:
+  - name: rig
+    joints: [bone]
+").unwrap();
+    let gltf = compile(&model).unwrap();
+
+    let mesh = |skinned: bool| {
+      let node = gltf.nodes.iter().find(|it| it.mesh.is_some() && it.skin.is_some() == skinned);
+      &gltf.meshes[node.unwrap().mesh.unwrap() as usize].primitives[0].attributes
+    };
+    assert!(mesh(true).joints_0.is_some() && mesh(true).weights_0.is_some());
+    assert!(mesh(false).joints_0.is_none() && mesh(false).weights_0.is_none());
+    assert_eq!(mesh(true).position, mesh(false).position);
+  }
+
+  fn trs_matrix(scale: Vector3, rotation: glam::Quat, offset: Vector3) -> glam::Mat4 {
+    glam::Mat4::from_scale_rotation_translation(glam::vec3(scale.x, scale.y, scale.z), rotation,
+      glam::vec3(offset.x, offset.y, offset.z))
+  }
+
+  fn node_matrix(node: &Node) -> glam::Mat4 {
+    let rotation = node.rotation.map_or(glam::Quat::IDENTITY, |it| it.to_quat());
+    trs_matrix(node.scale.unwrap_or(Vector3::ONE), rotation, node.offset.unwrap_or_default())
+  }
+
+  fn expand_node(src: &str) -> (Node, Vec<Node>) {
+    let model = crate::parse(src).unwrap();
+    (model.nodes[0].clone(), expand_arrays(&model).nodes)
+  }
+
+  #[test]
+  fn array_copies_are_placed_after_the_node_transform() {
+    let sources = [
+      // Non-uniform steps are exact for nodes without a rotation of their own.
+      "nodes:
+  - name: post
+    offset: { y: 1 }
+    scale: { x: 1, y: 2, z: 1 }
+    array:
+      Linear: { count: 3, offset: { x: 2 }, rotation: { z: 30 }, scale: { x: 2, y: 1, z: 0.5 } }",
+      "nodes:
+  - name: spoke
+    offset: { x: 1, y: 0.5 }
+    rotation: { x: 30, y: 10 }
+    scale: { x: 0.5, y: 3, z: 1 }
+    array: { Radial: { count: 5, axis: Y } }",
+      "nodes:
+  - name: tile
+    offset: { z: -1 }
+    rotation: { y: 45 }
+    array: { Grid: { count: { x: 2, y: 3 }, spacing: { x: 1.5, y: 2 } } }",
+    ];
+
+    for src in sources {
+      let (node, copies) = expand_node(src);
+      let pattern = node.array.as_ref().unwrap();
+      assert_eq!(copies.len() as u32, pattern.count());
+      for (i, copy) in copies.iter().enumerate() {
+        let (scale, rotation, offset) = pattern.transform(i as u32);
+        let expected = trs_matrix(scale, rotation, offset) * node_matrix(&node);
+        assert!(node_matrix(copy).abs_diff_eq(expected, 1e-5), "{}: copy {}", node.name, i);
+      }
+    }
+  }
+
+  #[test]
+  fn uneven_array_scales_apply_along_the_axes_of_rotated_nodes() {
+    let (_, copies) = expand_node("nodes:
+  - name: board
+    rotation: { axis: { z: 1 }, angle: 90 }
+    array: { Linear: { count: 2, scale: { x: 2, y: 1, z: 1 } } }");
+
+    // A matrix would stretch the copy along the x axis of the array, which is the y axis of the
+    // board, but the copy can only keep a scale along its own axes.
+    let copy = node_matrix(&copies[1]);
+    assert!(copy.transform_vector3(glam::Vec3::X).abs_diff_eq(glam::vec3(0.0, 2.0, 0.0), 1e-5));
+    assert!(copy.transform_vector3(glam::Vec3::Y).abs_diff_eq(glam::vec3(-1.0, 0.0, 0.0), 1e-5));
+  }
+
+  #[test]
+  fn normals_have_unit_length_and_point_out() {
+    for flat in [false, true] {
+      let gltf = compile_src(&format!("
+flat: {}
+meshes: [{{ name: ball, primitives: [{{ geometry: [Icosphere: {{ divides: 1 }}] }}] }}]
+nodes: [{{ name: ball, mesh: ball }}]
+", flat));
+      let attributes = &gltf.meshes[0].primitives[0].attributes;
+      let positions = vectors(&gltf, attributes.position.unwrap());
+      let normals = vectors(&gltf, attributes.normal.unwrap());
+      assert_eq!(normals.len(), positions.len());
+
+      // Face normals of a sphere lean a little away from their corners, smooth normals do not.
+      let min_dot = if flat { 0.9 } else { 0.999 };
+      for (position, normal) in positions.iter().zip(&normals) {
+        assert!((normal.length() - 1.0).abs() < 1e-4, "{:?}", normal);
+        assert!(normal.dot(position.normalize()) > min_dot, "{:?} at {:?}", normal, position);
+      }
+      if flat {
+        for corners in normals.chunks(3) {
+          assert!(corners[0] == corners[1] && corners[1] == corners[2]);
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn shared_vertices_are_indexed_unless_flat() {
+    let gltf = compile_src("
+meshes:
+  - name: ball
+    primitives:
+      - geometry: [Icosphere: { divides: 1 }]
+      - geometry: [Icosphere: { divides: 1 }]
+        flat: true
+nodes: [{ name: ball, mesh: ball }]
+");
+    let (smooth, flat) = (&gltf.meshes[0].primitives[0], &gltf.meshes[0].primitives[1]);
+    let positions = vectors(&gltf, smooth.attributes.position.unwrap());
+    let indices = indices(&gltf, smooth.indices.unwrap());
+    assert_eq!(gltf.accessors[smooth.indices.unwrap() as usize].component_type, 5123);
+    assert!(positions.len() < indices.len());
+    assert!(indices.iter().all(|it| (*it as usize) < positions.len()));
+
+    let flat_positions = vectors(&gltf, flat.attributes.position.unwrap());
+    assert!(flat.indices.is_none());
+    let indexed: Vec<glam::Vec3> = indices.iter().map(|it| positions[*it as usize]).collect();
+    assert_eq!(flat_positions, indexed);
+  }
+
+  #[test]
+  fn large_meshes_get_32_bit_indices() {
+    let mut buffer_data = Vec::new();
+    let mut buffer_views = Vec::new();
+    let mut accessors = Vec::new();
+    let triangles = [geometry_buffer::Triangle([0, 1, 70_000])];
+    for (vertex_count, component_type) in [(65_534, 5123), (70_001, 5125)] {
+      let accessor = push_indices(&mut buffer_data, &mut buffer_views, &mut accessors, &triangles,
+        vertex_count);
+      assert_eq!(accessors[accessor as usize].component_type, component_type);
+    }
+
+    let gltf = gltf::Gltf {
+      buffers: vec![gltf::Buffer::new(buffer_data)],
+      buffer_views,
+      accessors,
+      ..compile_src("nodes: []")
+    };
+    assert_eq!(indices(&gltf, 1), [0, 1, 70_000]);
+  }
+
+  #[test]
+  fn material_parameters_are_written_through() {
+    let gltf = compile_src("
+materials:
+  - name: plain
+  - { name: glow, emissive: red, doubleSided: false }
+  - { name: lamp, emissiveTexture: { image: lamp.png } }
+  - { name: leaf, alphaMode: Mask, alphaCutoff: 0.25 }
+  - { name: glass, alphaMode: Blend, baseColor: [1.0, 1.0, 1.0, 0.5] }
+");
+    let materials = serde_json::to_value(&gltf.materials).unwrap();
+    let pbr = |alpha: f32| serde_json::json!({
+      "baseColorFactor": [1.0, 1.0, 1.0, alpha], "metallicFactor": 0.0, "roughnessFactor": 0.0,
+    });
+    assert_eq!(materials, serde_json::json!([
+      { "name": "plain", "pbrMetallicRoughness": pbr(1.0), "doubleSided": true },
+      {
+        "name": "glow", "pbrMetallicRoughness": pbr(1.0), "emissiveFactor": [1.0, 0.0, 0.0],
+        "doubleSided": false,
+      },
+      {
+        "name": "lamp", "pbrMetallicRoughness": pbr(1.0), "emissiveFactor": [1.0, 1.0, 1.0],
+        "emissiveTexture": { "index": 0, "texCoord": 0 }, "doubleSided": true,
+      },
+      {
+        "name": "leaf", "pbrMetallicRoughness": pbr(1.0), "alphaMode": "MASK",
+        "alphaCutoff": 0.25, "doubleSided": true,
+      },
+      {
+        "name": "glass", "pbrMetallicRoughness": pbr(0.5), "alphaMode": "BLEND",
+        "doubleSided": true,
+      },
+    ]));
+  }
+
+  #[test]
+  fn morph_targets_store_differences_and_animate_weights() {
+    let gltf = compile_src("
+meshes:
+  - name: blob
+    primitives: [{ geometry: [Cube: { size: { x: 1, y: 1, z: 1 } }] }]
+    targets:
+      - { name: raised, weight: 0.5, offset: { y: 1 } }
+      - { name: grown, scale: { x: 2, y: 2, z: 2 } }
+nodes: [{ name: blob, mesh: blob }]
+animations:
+  - name: pulse
+    channels:
+      - nodes: [blob]
+        target: Weights
+        keyframes: [[0, { weights: [0, 1] }], [1, { weights: [1, 0] }]]
+");
+    let mesh = &gltf.meshes[0];
+    assert_eq!(mesh.weights, [0.5, 0.0]);
+    assert_eq!(mesh.extras.as_ref().unwrap().target_names, ["raised", "grown"]);
+
+    let primitive = &mesh.primitives[0];
+    let positions = vectors(&gltf, primitive.attributes.position.unwrap());
+    let raised = vectors(&gltf, primitive.targets[0].position.unwrap());
+    let grown = vectors(&gltf, primitive.targets[1].position.unwrap());
+    assert!(raised.iter().all(|it| *it == glam::Vec3::Y));
+    assert_eq!(grown, positions);
+    // Scaling keeps the directions of the faces, so their normals do not change.
+    let normals = vectors(&gltf, primitive.targets[1].normal.unwrap());
+    assert!(normals.iter().all(|it| it.abs_diff_eq(glam::Vec3::ZERO, 1e-6)));
+
+    let animation = &gltf.animations[0];
+    assert!(matches!(animation.channels[0].target.path, gltf::Path::Weights));
+    let sampler = &animation.samplers[animation.channels[0].sampler as usize];
+    assert_eq!(floats(&gltf, sampler.input), [0.0, 1.0]);
+    assert_eq!(floats(&gltf, sampler.output), [0.0, 1.0, 1.0, 0.0]);
+  }
+
+  #[test]
+  fn cameras_and_lights_are_attached_to_their_nodes() {
+    let gltf = compile_src("
+nodes:
+  - name: eye
+    camera: { Perspective: { yfov: 60, znear: 0.1 } }
+  - name: map
+    camera: { Orthographic: { xmag: 2, ymag: 1, znear: 0, zfar: 10 } }
+  - name: sun
+    light: { Directional: { color: white, intensity: 2 } }
+  - name: torch
+    light: { Spot: { color: red, range: 5, innerConeAngle: 15, outerConeAngle: 30 } }
+");
+    let json = serde_json::to_value(&gltf).unwrap();
+    let float = |value: &serde_json::Value| value.as_f64().unwrap() as f32;
+
+    let perspective = &json["cameras"][0];
+    assert_eq!(perspective["type"], "perspective");
+    assert!((float(&perspective["perspective"]["yfov"]) - 60f32.to_radians()).abs() < 1e-6);
+    assert!(perspective["perspective"].get("zfar").is_none());
+    assert_eq!(json["cameras"][1]["type"], "orthographic");
+    assert_eq!(json["cameras"][1]["orthographic"]["xmag"], 2.0);
+
+    assert_eq!(json["extensionsUsed"], serde_json::json!(["KHR_lights_punctual"]));
+    let lights = &json["extensions"]["KHR_lights_punctual"]["lights"];
+    assert_eq!(lights[0]["type"], "directional");
+    assert_eq!(lights[0]["intensity"], 2.0);
+    assert_eq!(lights[1]["type"], "spot");
+    assert_eq!(lights[1]["color"], serde_json::json!([1.0, 0.0, 0.0]));
+    assert_eq!(lights[1]["range"], 5.0);
+    assert!((float(&lights[1]["spot"]["outerConeAngle"]) - 30f32.to_radians()).abs() < 1e-6);
+
+    let cameras: Vec<u32> = gltf.nodes.iter().filter_map(|it| it.camera).collect();
+    let lights: Vec<u32> = gltf.nodes.iter()
+      .filter_map(|it| it.extensions.as_ref().map(|it| it.lights_punctual.light))
+      .collect();
+    assert_eq!((cameras.len(), lights.len()), (2, 2));
+    assert!(cameras.contains(&0) && cameras.contains(&1));
+    assert!(lights.contains(&0) && lights.contains(&1));
+  }
+
+  #[test]
+  fn models_without_lights_do_not_use_the_extension() {
+    let json = serde_json::to_value(compile_src("nodes: [{ name: empty }]")).unwrap();
+    assert!(json.get("extensions").is_none());
+    assert!(json.get("extensionsUsed").is_none());
+  }
+
+  #[test]
+  fn scenes_pick_root_nodes_and_a_default() {
+    let nodes = "
+nodes:
+  - { name: a, offset: { x: 1 } }
+  - { name: b, offset: { x: 2 }, children: [{ name: c, offset: { x: 3 } }] }
+  - { name: d, offset: { x: 4 } }
+";
+    let offsets = |gltf: &gltf::Gltf, scene: usize| -> Vec<f32> {
+      gltf.scenes[scene].nodes.iter()
+        .map(|it| gltf.nodes[*it as usize].translation.unwrap()[0])
+        .collect()
+    };
+
+    let gltf = compile_src(nodes);
+    assert_eq!(gltf.scene, 0);
+    assert_eq!(gltf.scenes.len(), 1);
+    assert_eq!(offsets(&gltf, 0), [1.0, 2.0, 4.0]);
+
+    let gltf = compile_src(&format!("{}
+scenes:
+  - {{ name: model, nodes: [b] }}
+  - {{ name: showcase, nodes: [d, a, b] }}
+defaultScene: showcase
+", nodes));
+    assert_eq!(gltf.scene, 1);
+    let names: Vec<_> = gltf.scenes.iter().map(|it| it.name.as_deref()).collect();
+    assert_eq!(names, [Some("model"), Some("showcase")]);
+    assert_eq!(offsets(&gltf, 0), [2.0]);
+    assert_eq!(offsets(&gltf, 1), [4.0, 1.0, 2.0]);
+  }
+
+  #[test]
+  fn template_instances_copy_the_subtree_and_share_meshes() {
+    let src = "
+meshes:
+  - name: plank
+    primitives: [{ geometry: [Cube: { size: { x: 1, y: 0.1, z: 0.2 } }] }]
+materials: [{ name: paint }]
+templates:
+  - name: board
+    mesh: plank
+    offset: { y: 1 }
+    children: [{ name: nail, mesh: plank, scale: { x: 0.1, y: 0.1, z: 0.1 } }]
+  - name: pair
+    children:
+      - { name: left, template: board }
+      - { name: right, template: board, offset: { x: 1 } }
+nodes:
+  - { name: a, template: pair }
+  - { name: b, template: board, material: paint }
+";
+    let model = expand_templates(&crate::parse(src).unwrap());
+    let mut nodes = Vec::new();
+    let mut stack: Vec<&Node> = model.nodes.iter().collect();
+    while let Some(node) = stack.pop() {
+      nodes.push((node.name.as_str(), node.offset.map(|it| [it.x, it.y, it.z]),
+        node.material.as_deref()));
+      stack.extend(&node.children);
+    }
+    nodes.sort_by_key(|it| it.0);
+    assert_eq!(nodes, [
+      ("a", None, None),
+      ("a/left", Some([0.0, 1.0, 0.0]), None),
+      ("a/left/nail", None, None),
+      ("a/right", Some([1.0, 0.0, 0.0]), None),
+      ("a/right/nail", None, None),
+      ("b", Some([0.0, 1.0, 0.0]), Some("paint")),
+      ("b/nail", None, Some("paint")),
+    ]);
+
+    // Every plank uses the same mesh, except for the painted ones, which share a copy.
+    let gltf = compile_src(src);
+    let mut meshes: Vec<u32> = gltf.nodes.iter().filter_map(|it| it.mesh).collect();
+    meshes.sort_unstable();
+    assert_eq!(meshes, [0, 0, 0, 0, 1, 1]);
+    assert_eq!(gltf.meshes[1].primitives[0].attributes.position,
+      gltf.meshes[0].primitives[0].attributes.position);
+  }
+}
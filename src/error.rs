@@ -0,0 +1,263 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::{DocumentPath, Location};
+
+/// An error raised while loading, compiling or writing a model.
+///
+/// Errors raised from a file carry the path of the file, and errors that concern a part of the
+/// model carry the path to it in the document and, when the source is known, its line and column.
+#[derive(Debug)]
+pub enum Error {
+  /// Reading or writing a file failed.
+  Io {
+    file: Option<PathBuf>,
+    source: std::io::Error,
+  },
+  /// Serializing the glTF document failed.
+  Json(serde_json::Error),
+  /// The YAML is malformed or does not match the model format.
+  Parse {
+    file: Option<PathBuf>,
+    location: Option<Location>,
+    source: serde_yaml::Error,
+  },
+  /// A name refers to a material, mesh, node, scene, skin or template that does not exist.
+  Reference {
+    file: Option<PathBuf>,
+    path: DocumentPath,
+    location: Option<Location>,
+    kind: ReferenceKind,
+    name: String,
+  },
+  /// A geometry entry generated invalid vertices or triangles.
+  Geometry {
+    file: Option<PathBuf>,
+    path: DocumentPath,
+    location: Option<Location>,
+    message: String,
+  },
+  /// A value in the model is not allowed, found while validating the model.
+  Invalid {
+    file: Option<PathBuf>,
+    path: DocumentPath,
+    location: Option<Location>,
+    message: String,
+  },
+  /// Several problems were found at once.
+  Multiple(Vec<Error>),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ReferenceKind {
+  Material,
+  Mesh,
+  Node,
+  Scene,
+  Skin,
+  Template,
+}
+
+impl Error {
+  pub fn geometry(path: DocumentPath, message: impl Into<String>) -> Self {
+    Error::Geometry { file: None, path, location: None, message: message.into() }
+  }
+
+  pub fn invalid(path: DocumentPath, message: impl Into<String>) -> Self {
+    Error::Invalid { file: None, path, location: None, message: message.into() }
+  }
+
+  /// Combine a non-empty list of errors into one error.
+  pub fn from_errors(mut errors: Vec<Error>) -> Self {
+    if errors.len() == 1 {
+      errors.remove(0)
+    } else {
+      Error::Multiple(errors)
+    }
+  }
+
+  /// List every individual error, flattening `Error::Multiple`.
+  pub fn errors(&self) -> Vec<&Error> {
+    match self {
+      Error::Multiple(errors) => errors.iter().flat_map(|it| it.errors()).collect(),
+      _ => vec![self],
+    }
+  }
+
+  pub fn reference(path: DocumentPath, kind: ReferenceKind, name: impl Into<String>) -> Self {
+    Error::Reference { file: None, path, location: None, kind, name: name.into() }
+  }
+
+  pub fn file(&self) -> Option<&Path> {
+    match self {
+      Error::Io { file, .. }
+      | Error::Parse { file, .. }
+      | Error::Reference { file, .. }
+      | Error::Geometry { file, .. }
+      | Error::Invalid { file, .. } => file.as_deref(),
+      Error::Json(_) | Error::Multiple(_) => None,
+    }
+  }
+
+  pub fn location(&self) -> Option<Location> {
+    match self {
+      Error::Parse { location, .. }
+      | Error::Reference { location, .. }
+      | Error::Geometry { location, .. }
+      | Error::Invalid { location, .. } => *location,
+      Error::Io { .. } | Error::Json(_) | Error::Multiple(_) => None,
+    }
+  }
+
+  pub fn path(&self) -> Option<&DocumentPath> {
+    match self {
+      Error::Reference { path, .. }
+      | Error::Geometry { path, .. }
+      | Error::Invalid { path, .. } => Some(path),
+      Error::Io { .. } | Error::Json(_) | Error::Parse { .. } | Error::Multiple(_) => None,
+    }
+  }
+
+  pub(crate) fn path_mut(&mut self) -> Option<&mut DocumentPath> {
+    match self {
+      Error::Reference { path, .. }
+      | Error::Geometry { path, .. }
+      | Error::Invalid { path, .. } => Some(path),
+      Error::Io { .. } | Error::Json(_) | Error::Parse { .. } | Error::Multiple(_) => None,
+    }
+  }
+
+  /// Attach the file the error was raised from, unless the error already names a file.
+  pub fn in_file(self, path: impl Into<PathBuf>) -> Self {
+    let path = path.into();
+    match self {
+      Error::Multiple(errors) => {
+        Error::Multiple(errors.into_iter().map(|it| it.in_file(path.clone())).collect())
+      }
+      mut err => {
+        match &mut err {
+          Error::Io { file, .. }
+          | Error::Parse { file, .. }
+          | Error::Reference { file, .. }
+          | Error::Geometry { file, .. }
+          | Error::Invalid { file, .. } => {
+            if file.is_none() {
+              *file = Some(path);
+            }
+          }
+          Error::Json(_) | Error::Multiple(_) => {}
+        }
+        err
+      }
+    }
+  }
+
+  /// Resolve the line and column of the document path of the error in the YAML source.
+  pub fn locate(mut self, src: &str) -> Self {
+    match &mut self {
+      Error::Reference { path, location, .. }
+      | Error::Geometry { path, location, .. }
+      | Error::Invalid { path, location, .. } => {
+        if location.is_none() {
+          *location = path.locate(src);
+        }
+      }
+      Error::Multiple(errors) => {
+        *errors = std::mem::take(errors).into_iter().map(|it| it.locate(src)).collect();
+      }
+      Error::Io { .. } | Error::Json(_) | Error::Parse { .. } => {}
+    }
+    self
+  }
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if let Error::Multiple(errors) = self {
+      for (i, err) in errors.iter().enumerate() {
+        if i > 0 {
+          writeln!(f)?;
+        }
+        write!(f, "{}", err)?;
+      }
+      return Ok(());
+    }
+
+    match (self.file(), self.location()) {
+      (Some(file), Some(location)) => write!(f, "{}:{}: ", file.display(), location)?,
+      (Some(file), None) => write!(f, "{}: ", file.display())?,
+      (None, Some(location)) => write!(f, "line {} column {}: ", location.line, location.column)?,
+      (None, None) => {}
+    }
+
+    if let Some(path) = self.path() {
+      if !path.is_root() {
+        write!(f, "{}: ", path)?;
+      }
+    }
+
+    match self {
+      Error::Io { source, .. } => write!(f, "{}", source),
+      Error::Json(err) => write!(f, "{}", err),
+      Error::Parse { source, .. } => {
+        // The YAML error repeats the location at the end of its message, as it was before
+        // expressions were replaced.
+        let message = source.to_string();
+        match source.location() {
+          Some(location) => {
+            let suffix = format!(" at line {} column {}", location.line(), location.column());
+            write!(f, "{}", message.strip_suffix(&suffix).unwrap_or(&message))
+          }
+          None => write!(f, "{}", message),
+        }
+      }
+      Error::Reference { kind, name, .. } => write!(f, "Cannot find {} '{}'", kind, name),
+      Error::Geometry { message, .. } | Error::Invalid { message, .. } => write!(f, "{}", message),
+      Error::Multiple(_) => Ok(()),
+    }
+  }
+}
+
+impl std::error::Error for Error {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      Error::Io { source, .. } => Some(source),
+      Error::Json(err) => Some(err),
+      Error::Parse { source, .. } => Some(source),
+      _ => None,
+    }
+  }
+}
+
+impl fmt::Display for ReferenceKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ReferenceKind::Material => write!(f, "material"),
+      ReferenceKind::Mesh => write!(f, "mesh"),
+      ReferenceKind::Node => write!(f, "node"),
+      ReferenceKind::Scene => write!(f, "scene"),
+      ReferenceKind::Skin => write!(f, "skin"),
+      ReferenceKind::Template => write!(f, "template"),
+    }
+  }
+}
+
+impl From<std::io::Error> for Error {
+  fn from(source: std::io::Error) -> Self {
+    Error::Io { file: None, source }
+  }
+}
+
+impl From<serde_json::Error> for Error {
+  fn from(err: serde_json::Error) -> Self {
+    Error::Json(err)
+  }
+}
+
+impl From<serde_yaml::Error> for Error {
+  fn from(source: serde_yaml::Error) -> Self {
+    let location = source.location()
+      .map(|it| Location { line: it.line(), column: it.column() });
+    Error::Parse { file: None, location, source }
+  }
+}
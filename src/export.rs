@@ -0,0 +1,401 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::gltf::{Buffer, BufferView, Gltf};
+use crate::Error;
+
+const GLB_MAGIC: u32 = 0x4654_6C67; // "glTF"
+const GLB_VERSION: u32 = 2;
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A; // "JSON"
+const GLB_CHUNK_BIN: u32 = 0x004E_4942; // "BIN\0"
+
+/// How a compiled model and its buffers are written to disk.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Format {
+  /// A single `.gltf` file with buffers embedded as base64 data URIs.
+  Embedded,
+  /// A single binary `.glb` file.
+  Binary,
+  /// A `.gltf` file referencing an external `.bin` file.
+  Separate,
+}
+
+impl Format {
+  pub fn extension(&self) -> &'static str {
+    match self {
+      Format::Embedded | Format::Separate => "gltf",
+      Format::Binary => "glb",
+    }
+  }
+}
+
+impl FromStr for Format {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "embedded" | "gltf" => Ok(Format::Embedded),
+      "binary" | "glb" => Ok(Format::Binary),
+      "separate" | "bin" => Ok(Format::Separate),
+      _ => Err(format!("Unknown output format '{}' (expected embedded, binary or separate)", s)),
+    }
+  }
+}
+
+/// Read the data of every image referenced by a relative path, resolving the paths against `dir`.
+/// Loaded images are embedded in the output or copied next to it, depending on the format.
+pub fn load_images(gltf: &mut Gltf, dir: &Path) -> Result<(), Error> {
+  for image in &mut gltf.images {
+    if let (Some(uri), None) = (&image.uri, &image.data) {
+      if uri.starts_with("data:") {
+        continue;
+      }
+      let image_path = dir.join(uri);
+      let data = std::fs::read(&image_path).map_err(|err| Error::from(err).in_file(&image_path))?;
+      image.data = Some(data);
+    }
+  }
+  Ok(())
+}
+
+/// Write the model to `path` with the extension of the format appended, returning the paths of
+/// every file written.
+pub fn write(gltf: &Gltf, format: Format, path: &Path) -> Result<Vec<PathBuf>, Error> {
+  let file_path = with_extension(path, format.extension());
+
+  match format {
+    Format::Embedded => {
+      write_file(&file_path, to_embedded_json(gltf)?)?;
+      Ok(vec![file_path])
+    }
+    Format::Binary => {
+      write_file(&file_path, to_glb(gltf)?)?;
+      Ok(vec![file_path])
+    }
+    Format::Separate => {
+      let bin_path = with_extension(path, "bin");
+      let bin_name = bin_path.file_name()
+        .and_then(|it| it.to_str())
+        .unwrap_or_default()
+        .to_string();
+
+      let mut output = gltf.clone();
+      let mut written = vec![file_path.clone()];
+      let dir = path.parent().unwrap_or_else(|| Path::new(""));
+      let mut image_names: Vec<String> = Vec::new();
+      for (i, image) in output.images.iter_mut().enumerate() {
+        if let Some(data) = image.data.take() {
+          let mut name = image.uri.as_deref()
+            .and_then(|it| Path::new(it).file_name())
+            .and_then(|it| it.to_str())
+            .unwrap_or("image")
+            .to_string();
+          if image_names.contains(&name) {
+            name = format!("{}-{}", i, name);
+          }
+
+          let image_path = dir.join(&name);
+          write_file(&image_path, data)?;
+          written.push(image_path);
+          image.uri = Some(name.clone());
+          image_names.push(name);
+        }
+      }
+
+      // Without any buffer data there is no binary file to refer to.
+      let bin = merge_buffers(&output);
+      if !bin.is_empty() {
+        write_file(&bin_path, bin)?;
+        written.insert(1, bin_path);
+      }
+      write_file(&file_path, to_separate_json(&output, &bin_name)?)?;
+      Ok(written)
+    }
+  }
+}
+
+fn write_file(path: &Path, contents: impl AsRef<[u8]>) -> Result<(), Error> {
+  std::fs::write(path, contents).map_err(|err| Error::from(err).in_file(path))
+}
+
+/// Append an extension without replacing anything after a dot in the file name.
+fn with_extension(path: &Path, extension: &str) -> PathBuf {
+  let mut output = path.as_os_str().to_owned();
+  output.push(".");
+  output.push(extension);
+  PathBuf::from(output)
+}
+
+/// Serialize the model as JSON with every buffer and loaded image embedded as a base64 data URI.
+pub fn to_embedded_json(gltf: &Gltf) -> serde_json::Result<String> {
+  let base64_config = base64::Config::new(base64::CharacterSet::Standard, false);
+
+  let mut output = gltf.clone();
+  for buffer in &mut output.buffers {
+    buffer.uri = Some(format!("data:application/octet-stream;base64,{}",
+      base64::encode_config(&buffer.data, base64_config)));
+  }
+  for image in &mut output.images {
+    if let Some(data) = image.data.take() {
+      let mime_type = image.mime_type.as_deref().unwrap_or("application/octet-stream");
+      image.uri = Some(format!("data:{};base64,{}", mime_type,
+        base64::encode_config(&data, base64_config)));
+    }
+  }
+
+  serde_json::to_string_pretty(&output)
+}
+
+/// Serialize the model as JSON referencing a single external binary file named `bin_name`,
+/// whose contents are given by `merge_buffers`. Images keep referring to their `uri`.
+pub fn to_separate_json(gltf: &Gltf, bin_name: &str) -> serde_json::Result<String> {
+  let output = merge_into_single_buffer(gltf, Some(bin_name.to_string()));
+  serde_json::to_string_pretty(&output)
+}
+
+/// Serialize the model as a binary glTF container with a JSON chunk and a single BIN chunk, which
+/// also holds every loaded image.
+pub fn to_glb(gltf: &Gltf) -> serde_json::Result<Vec<u8>> {
+  let gltf = &move_images_into_buffers(gltf);
+  let output = merge_into_single_buffer(gltf, None);
+
+  let mut json = serde_json::to_vec(&output)?;
+  while !json.len().is_multiple_of(4) {
+    json.push(b' ');
+  }
+
+  let mut bin = merge_buffers(gltf);
+  while !bin.len().is_multiple_of(4) {
+    bin.push(0);
+  }
+
+  let mut total_length = 12 + 8 + json.len();
+  if !bin.is_empty() {
+    total_length += 8 + bin.len();
+  }
+
+  let mut glb = Vec::with_capacity(total_length);
+  glb.extend_from_slice(&GLB_MAGIC.to_le_bytes());
+  glb.extend_from_slice(&GLB_VERSION.to_le_bytes());
+  glb.extend_from_slice(&(total_length as u32).to_le_bytes());
+
+  glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+  glb.extend_from_slice(&GLB_CHUNK_JSON.to_le_bytes());
+  glb.extend_from_slice(&json);
+
+  if !bin.is_empty() {
+    glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+    glb.extend_from_slice(&GLB_CHUNK_BIN.to_le_bytes());
+    glb.extend_from_slice(&bin);
+  }
+
+  Ok(glb)
+}
+
+/// Concatenate the data of every buffer, aligning each buffer to four bytes.
+pub fn merge_buffers(gltf: &Gltf) -> Vec<u8> {
+  let mut data = Vec::new();
+  for buffer in &gltf.buffers {
+    while !data.len().is_multiple_of(4) {
+      data.push(0);
+    }
+    data.extend_from_slice(&buffer.data);
+  }
+  data
+}
+
+/// Rewrite the model so that every buffer view points into the single buffer produced by
+/// `merge_buffers`.
+fn merge_into_single_buffer(gltf: &Gltf, uri: Option<String>) -> Gltf {
+  let mut offsets = Vec::new();
+  let mut byte_length: u32 = 0;
+  for buffer in &gltf.buffers {
+    while !byte_length.is_multiple_of(4) {
+      byte_length += 1;
+    }
+    offsets.push(byte_length);
+    byte_length += buffer.data.len() as u32;
+  }
+
+  let mut output = gltf.clone();
+  for buffer_view in &mut output.buffer_views {
+    buffer_view.byte_offset += offsets[buffer_view.buffer as usize];
+    buffer_view.buffer = 0;
+  }
+
+  output.buffers.clear();
+  if byte_length > 0 {
+    let mut buffer = Buffer::new(Vec::new());
+    buffer.uri = uri;
+    buffer.byte_length = byte_length;
+    output.buffers.push(buffer);
+  }
+
+  output
+}
+
+/// Move the data of every loaded image into a buffer of its own, referenced by a buffer view.
+fn move_images_into_buffers(gltf: &Gltf) -> Gltf {
+  let mut output = gltf.clone();
+  for image in &mut output.images {
+    if let Some(data) = image.data.take() {
+      output.buffer_views.push(BufferView {
+        buffer: output.buffers.len() as u32,
+        byte_offset: 0,
+        byte_length: data.len() as u32,
+        target: None,
+      });
+      output.buffers.push(Buffer::new(data));
+      image.buffer_view = Some(output.buffer_views.len() as u32 - 1);
+      image.uri = None;
+    }
+  }
+  output
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn compile(src: &str) -> Gltf {
+    crate::compile(&crate::parse(src).unwrap()).unwrap()
+  }
+
+  fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+  }
+
+  const TRIANGLE: &str = "
+meshes:
+  - name: triangle
+    primitives:
+      - geometry:
+          - Triangle: { points: [{ x: 0 }, { x: 1 }, { y: 1 }] }
+nodes:
+  - name: triangle
+    mesh: triangle
+";
+
+  #[test]
+  fn glb_chunks_are_padded_to_four_bytes() {
+    let mut gltf = compile(TRIANGLE);
+    gltf.buffers[0].data.push(7);
+    gltf.buffers[0].byte_length += 1;
+    let glb = to_glb(&gltf).unwrap();
+
+    assert_eq!(u32_at(&glb, 0), GLB_MAGIC);
+    assert_eq!(u32_at(&glb, 8) as usize, glb.len());
+
+    let json_length = u32_at(&glb, 12) as usize;
+    assert_eq!(json_length % 4, 0);
+    assert_eq!(u32_at(&glb, 16), GLB_CHUNK_JSON);
+    let json = &glb[20..20 + json_length];
+    assert!(serde_json::from_slice::<serde_json::Value>(json).is_ok());
+
+    let bin_start = 20 + json_length;
+    let bin_length = u32_at(&glb, bin_start) as usize;
+    assert_eq!(bin_length % 4, 0);
+    assert_eq!(u32_at(&glb, bin_start + 4), GLB_CHUNK_BIN);
+    assert_eq!(bin_start + 8 + bin_length, glb.len());
+
+    let data = &gltf.buffers[0].data;
+    let bin = &glb[bin_start + 8..];
+    assert_eq!(&bin[..data.len()], data.as_slice());
+    assert!(bin[data.len()..].iter().all(|it| *it == 0));
+  }
+
+  #[test]
+  fn merged_buffers_start_on_four_byte_boundaries() {
+    let mut gltf = compile(TRIANGLE);
+    gltf.buffers.insert(0, Buffer::new(vec![1, 2, 3]));
+    for buffer_view in &mut gltf.buffer_views {
+      buffer_view.buffer = 1;
+    }
+
+    let data = merge_buffers(&gltf);
+    assert_eq!(&data[..4], &[1, 2, 3, 0]);
+    let merged = merge_into_single_buffer(&gltf, None);
+    assert_eq!(merged.buffers.len(), 1);
+    assert_eq!(merged.buffers[0].byte_length as usize, data.len());
+    for (merged, original) in merged.buffer_views.iter().zip(&gltf.buffer_views) {
+      assert_eq!(merged.buffer, 0);
+      assert_eq!(merged.byte_offset, original.byte_offset + 4);
+    }
+  }
+
+  #[test]
+  fn models_without_data_have_no_buffers() {
+    let gltf = compile("nodes: [{ name: empty }]");
+    assert!(gltf.buffers.is_empty());
+
+    let glb = to_glb(&gltf).unwrap();
+    assert_eq!(20 + u32_at(&glb, 12) as usize, glb.len());
+
+    let json: serde_json::Value = serde_json::from_str(&to_embedded_json(&gltf).unwrap()).unwrap();
+    assert!(json.get("buffers").is_none());
+    assert!(json.get("bufferViews").is_none());
+  }
+
+  const TEXTURED: &str = "
+meshes:
+  - name: triangle
+    primitives:
+      - material: wood
+        geometry:
+          - Triangle: { points: [{ x: 0 }, { x: 1 }, { y: 1 }] }
+materials:
+  - name: wood
+    baseColorTexture: { image: textures/wood.png, wrapS: ClampToEdge, magFilter: Nearest }
+nodes:
+  - name: triangle
+    mesh: triangle
+";
+
+  fn json(src: &[u8]) -> serde_json::Value {
+    serde_json::from_slice(src).unwrap()
+  }
+
+  #[test]
+  fn textures_survive_every_format() {
+    let image = b"\x89PNG not really an image";
+    let dir = std::env::temp_dir()
+      .join(format!("declarative-models-formats-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("textures")).unwrap();
+    std::fs::create_dir_all(dir.join("out")).unwrap();
+    std::fs::write(dir.join("textures/wood.png"), image).unwrap();
+    let mut gltf = compile(TEXTURED);
+    load_images(&mut gltf, &dir).unwrap();
+    assert_eq!(gltf.images[0].uri.as_deref(), Some("textures/wood.png"));
+
+    let embedded = json(to_embedded_json(&gltf).unwrap().as_bytes());
+    let material = &embedded["materials"][0]["pbrMetallicRoughness"]["baseColorTexture"];
+    assert_eq!(material["index"], 0);
+    assert_eq!(embedded["textures"][0]["source"], 0);
+    let sampler = embedded["textures"][0]["sampler"].as_u64().unwrap() as usize;
+    assert_eq!(embedded["samplers"][sampler],
+      serde_json::json!({ "magFilter": 9728, "wrapS": 33071, "wrapT": 10497 }));
+    let uri = embedded["images"][0]["uri"].as_str().unwrap();
+    let data = uri.strip_prefix("data:image/png;base64,").unwrap();
+    assert_eq!(base64::decode(data).unwrap(), image);
+
+    let glb = to_glb(&gltf).unwrap();
+    let json_length = u32_at(&glb, 12) as usize;
+    let binary = json(&glb[20..20 + json_length]);
+    assert!(binary["images"][0].get("uri").is_none());
+    let view = &binary["bufferViews"][binary["images"][0]["bufferView"].as_u64().unwrap() as usize];
+    let start = 20 + json_length + 8 + view["byteOffset"].as_u64().unwrap() as usize;
+    assert_eq!(&glb[start..start + view["byteLength"].as_u64().unwrap() as usize], image);
+
+    let written = write(&gltf, Format::Separate, &dir.join("out/triangle")).unwrap();
+    let names: Vec<&str> = written.iter()
+      .map(|it| it.strip_prefix(dir.join("out")).unwrap().to_str().unwrap())
+      .collect();
+    let separate = json(&std::fs::read(&written[0]).unwrap());
+    let wood = std::fs::read(dir.join("out/wood.png")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(names, ["triangle.gltf", "triangle.bin", "wood.png"]);
+    assert_eq!(separate["images"][0]["uri"], "wood.png");
+    assert_eq!(separate["buffers"][0]["uri"], "triangle.bin");
+    assert_eq!(wood, image);
+  }
+}
@@ -0,0 +1,803 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use yaml_rust::parser::Event;
+use yaml_rust::scanner::TScalarStyle;
+
+use crate::location::walk_values;
+use crate::{DocumentPath, Error, Location, PathSegment};
+
+/// Replace every expression in YAML source by its value. The lines of the source are kept, so
+/// that the positions of errors found later still match the original, and columns can be mapped
+/// back with `Substituted::original_location`.
+///
+/// A string is an expression when it contains `${`, which is followed by a variable from
+/// `Model::variables` or by an expression using them, up to the matching `}`: `"${radius}"`,
+/// `"-${radius} * cos(45)"` and `"${2 * pi * radius}"` are all expressions, while `"1/2"` is
+/// left as it is. The definitions of variables are always expressions. Expressions use numbers
+/// such as `2`, `0.5` and `1e-3`, variables, `pi`, `+`, `-`, `*`, `/`, `%`, `^` and parentheses,
+/// and the functions `sin`, `cos`, `tan`, `asin`, `acos` and `atan` in degrees, `sqrt`, `abs`,
+/// `floor`, `ceil`, `round`, `min` and `max`. Vectors can be added, subtracted, and multiplied or divided by numbers, and
+/// `${name.x}` is the x axis of a vector. An expression that gives a vector is written as a
+/// `{ x, y, z }` mapping. Variables can only be used by names made of letters, digits and `_`.
+///
+/// Each of `overrides` sets a variable to the value of an expression instead of its definition,
+/// which is replaced by that value too. Only variables that the source defines can be set.
+pub(crate) fn substitute(src: &str, overrides: &[(String, String)]) -> Result<Substituted, Error> {
+  // Malformed YAML is left for the model parser to report.
+  let scalars = match scalars(src) {
+    Some(scalars) => scalars,
+    None => return Ok(Substituted { src: src.to_string(), shifts: Vec::new() }),
+  };
+
+  let mut variables = Variables {
+    definitions: HashMap::new(),
+    values: HashMap::new(),
+    overrides: overrides.iter().map(|(name, src)| (name.as_str(), src.as_str())).collect(),
+    resolving: Vec::new(),
+    errors: Vec::new(),
+  };
+  for scalar in &scalars {
+    if let Some((name, axis)) = variable_definition(&scalar.path) {
+      variables.definitions.entry(name).or_default().push((axis, scalar));
+    }
+  }
+
+  let chars: Vec<char> = src.chars().collect();
+  let mut errors = Vec::new();
+  for (name, _) in overrides {
+    if !variables.definitions.contains_key(name.as_str()) {
+      errors.push(Error::invalid(DocumentPath::root().key("variables"),
+        format!("Cannot set variable '{}', the model does not define it", name)));
+    }
+  }
+  let mut replacements = Vec::new();
+  for scalar in &scalars {
+    let quoted_or_plain = matches!(scalar.style,
+      TScalarStyle::Plain | TScalarStyle::SingleQuoted | TScalarStyle::DoubleQuoted);
+    let multiline = !quoted_or_plain || chars[scalar.start..scalar.end].contains(&'\n');
+    if multiline {
+      if scalar.value.contains("${") {
+        errors.push(Error::invalid(scalar.path.clone(), "Expressions must fit on a single line"));
+      }
+      continue;
+    }
+
+    // Problems with variables are reported where they are defined.
+    let definition = variable_definition(&scalar.path);
+    if let Some((name, axis)) = definition {
+      let value = variables.get(name);
+      if variables.overrides.contains_key(name) {
+        // The definition of a variable that is set is replaced by the value it is set to.
+        match (value, axis) {
+          (Ok(value), None) => replacements.push((scalar, value.to_string())),
+          (Ok(Value::Vector(vector)), Some(axis)) => {
+            replacements.push((scalar, vector[axis].to_string()));
+          }
+          _ => {}
+        }
+        continue;
+      }
+    }
+    let expression = match definition {
+      Some(_) => scalar.value.trim().parse::<f64>().is_err(),
+      None => scalar.value.contains("${"),
+    };
+    if !expression {
+      continue;
+    }
+
+    match variables.evaluate(&scalar.value) {
+      Ok(value) if value.is_finite() => replacements.push((scalar, value.to_string())),
+      _ if definition.is_some() => {}
+      Ok(_) => errors.push(Error::invalid(scalar.path.clone(), not_finite(&scalar.value))),
+      Err(Some(message)) => errors.push(Error::invalid(scalar.path.clone(), message)),
+      Err(None) => {}
+    }
+  }
+
+  errors.extend(variables.errors);
+  if !errors.is_empty() {
+    let mut errors: Vec<Error> = errors.into_iter().map(|it| it.locate(src)).collect();
+    errors.sort_by_key(|it| it.location().map(|it| (it.line, it.column)));
+    return Err(Error::from_errors(errors));
+  }
+
+  // Values are padded to the length of their expression, so that columns stay the same too
+  // unless a value is longer.
+  let mut out = String::with_capacity(src.len());
+  let mut shifts = Vec::new();
+  let (mut position, mut line, mut line_start) = (0, 1, 0);
+  for (scalar, value) in replacements {
+    for (i, c) in chars[position..scalar.start].iter().enumerate() {
+      if *c == '\n' {
+        line += 1;
+        line_start = position + i + 1;
+      }
+    }
+    out.extend(&chars[position..scalar.start]);
+
+    let from = scalar.end - scalar.start;
+    let value = format!("{:width$}", value, width = from);
+    let to = value.chars().count();
+    if to > from {
+      shifts.push(Shift { line, column: scalar.start - line_start + 1, from, to });
+    }
+    out.push_str(&value);
+    position = scalar.end;
+  }
+  out.extend(&chars[position..]);
+  Ok(Substituted { src: out, shifts })
+}
+
+/// YAML source with its expressions replaced by their values.
+pub(crate) struct Substituted {
+  pub src: String,
+  /// The values that are longer than the expressions they replace, in the order of the source.
+  shifts: Vec<Shift>,
+}
+
+/// A value that moves the rest of its line, with the line and column its expression starts at,
+/// and the number of characters of the expression and of the value.
+struct Shift {
+  line: usize,
+  column: usize,
+  from: usize,
+  to: usize,
+}
+
+impl Substituted {
+  /// The location in the original source of a location in the substituted source. Locations
+  /// inside a value are moved to the start of its expression.
+  pub fn original_location(&self, location: Location) -> Location {
+    let mut offset = 0;
+    for shift in self.shifts.iter().filter(|it| it.line == location.line) {
+      let start = shift.column + offset;
+      if location.column < start {
+        break;
+      }
+      if location.column < start + shift.to {
+        return Location { line: location.line, column: shift.column };
+      }
+      offset += shift.to - shift.from;
+    }
+    Location { line: location.line, column: location.column - offset }
+  }
+}
+
+fn not_finite(expression: &str) -> String {
+  format!("Expression '{}' does not give a finite number", expression)
+}
+
+/// The name of the variable a scalar at `path` defines, and the axis it sets if the variable is a
+/// vector.
+fn variable_definition(path: &DocumentPath) -> Option<(&str, Option<usize>)> {
+  use PathSegment::Key;
+
+  match path.0.as_slice() {
+    [Key(variables), Key(name)] if variables == "variables" => Some((name, None)),
+    [Key(variables), Key(name), Key(axis)] if variables == "variables" => {
+      let axis = ["x", "y", "z"].iter().position(|it| it == axis)?;
+      Some((name, Some(axis)))
+    }
+    _ => None,
+  }
+}
+
+/// A scalar in the YAML source that is not a mapping key, with the range of characters it spans,
+/// including any quotes.
+struct Scalar {
+  path: DocumentPath,
+  value: String,
+  style: TScalarStyle,
+  start: usize,
+  end: usize,
+}
+
+/// Every scalar value in the source, or `None` if the source is not valid YAML.
+fn scalars(src: &str) -> Option<Vec<Scalar>> {
+  let chars: Vec<char> = src.chars().collect();
+  let mut scalars = Vec::new();
+  walk_values(src, |path, event, marker| {
+    if let Event::Scalar(value, style, ..) = event {
+      let start = marker.index();
+      let end = scalar_end(&chars, start, *style, value);
+      scalars.push(Scalar { path, value: value.clone(), style: *style, start, end });
+    }
+    true
+  })?;
+  Some(scalars)
+}
+
+/// The index of the character after a scalar that starts at `start`. For plain scalars folded
+/// from several lines, the end is only used to tell that they span a line break.
+fn scalar_end(chars: &[char], start: usize, style: TScalarStyle, value: &str) -> usize {
+  let mut i = start + 1;
+  match style {
+    TScalarStyle::DoubleQuoted => {
+      while i < chars.len() && chars[i] != '"' {
+        i += if chars[i] == '\\' { 2 } else { 1 };
+      }
+      (i + 1).min(chars.len())
+    }
+    TScalarStyle::SingleQuoted => {
+      while i < chars.len() && (chars[i] != '\'' || chars.get(i + 1) == Some(&'\'')) {
+        i += if chars[i] == '\'' { 2 } else { 1 };
+      }
+      (i + 1).min(chars.len())
+    }
+    TScalarStyle::Plain => {
+      let end = (start + value.chars().count()).min(chars.len());
+      if chars[start..end].iter().copied().eq(value.chars()) {
+        end
+      } else {
+        chars[start..].iter().position(|it| *it == '\n').map_or(chars.len(), |it| start + it + 1)
+      }
+    }
+    _ => start,
+  }
+}
+
+/// The variables of a document, each evaluated when it is first used.
+struct Variables<'a> {
+  /// The scalars that define each variable, with the axis they set if the variable is a vector.
+  definitions: HashMap<&'a str, Vec<(Option<usize>, &'a Scalar)>>,
+  /// The value of every variable evaluated so far, or `None` if it failed.
+  values: HashMap<&'a str, Option<Value>>,
+  /// The expressions that variables are set to instead of their definitions.
+  overrides: HashMap<&'a str, &'a str>,
+  /// The variables being evaluated, to find variables that depend on themselves.
+  resolving: Vec<&'a str>,
+  /// Problems found in the definitions of variables.
+  errors: Vec<Error>,
+}
+
+impl<'a> Variables<'a> {
+  /// Evaluate an expression. Fails with a message, or with `None` if the expression uses a
+  /// variable that failed.
+  fn evaluate(&mut self, src: &str) -> Result<Value, Option<String>> {
+    let expression = ExpressionParser::parse(src)
+      .map_err(|err| format!("Invalid expression '{}': {}", src, err))?;
+    self.evaluate_expression(&expression)
+  }
+
+  fn evaluate_expression(&mut self, expression: &Expression) -> Result<Value, Option<String>> {
+    Ok(match expression {
+      Expression::Number(number) => Value::Number(*number),
+      Expression::Variable(name) => match name.split_once('.') {
+        Some((name, axis)) => {
+          let axis = ["x", "y", "z"].iter().position(|it| *it == axis)
+            .ok_or_else(|| format!("Unknown axis '{}' of variable '{}'", axis, name))?;
+          match self.get(name)? {
+            Value::Vector(vector) => Value::Number(vector[axis]),
+            Value::Number(_) => return Err(Some(format!("Variable '{}' is not a vector", name))),
+          }
+        }
+        None => self.get(name)?,
+      },
+      Expression::Negate(expression) => match self.evaluate_expression(expression)? {
+        Value::Number(number) => Value::Number(-number),
+        Value::Vector([x, y, z]) => Value::Vector([-x, -y, -z]),
+      },
+      Expression::Binary(operator, a, b) => {
+        let a = self.evaluate_expression(a)?;
+        let b = self.evaluate_expression(b)?;
+        binary(*operator, a, b)?
+      }
+      Expression::Call(function, arguments) => {
+        let mut numbers = Vec::new();
+        for argument in arguments {
+          match self.evaluate_expression(argument)? {
+            Value::Number(number) => numbers.push(number),
+            Value::Vector(_) => {
+              return Err(Some(format!("'{}' takes numbers, found a vector", function)));
+            }
+          }
+        }
+        Value::Number(call(function, &numbers)?)
+      }
+    })
+  }
+
+  /// The value of a variable, evaluated the first time it is used.
+  fn get(&mut self, name: &str) -> Result<Value, Option<String>> {
+    if let Some(value) = self.values.get(name) {
+      return value.ok_or(None);
+    }
+    let (name, definition) = match self.definitions.get_key_value(name) {
+      Some((name, definition)) => (*name, definition.clone()),
+      None => return Err(Some(format!("Unknown variable '{}'", name))),
+    };
+    if let Some(start) = self.resolving.iter().position(|it| *it == name) {
+      return Err(Some(format!("Variable '{}' depends on itself: {} -> {}", name,
+        self.resolving[start..].join(" -> "), name)));
+    }
+
+    self.resolving.push(name);
+    let value = match self.overrides.get(name).copied() {
+      Some(src) => self.evaluate_override(name, src, &definition),
+      None => self.evaluate_definition(&definition),
+    };
+    self.resolving.pop();
+
+    self.values.insert(name, value);
+    value.ok_or(None)
+  }
+
+  /// The value of a variable from the scalars that define it, or `None` if it failed.
+  fn evaluate_definition(&mut self, definition: &[(Option<usize>, &Scalar)]) -> Option<Value> {
+    let mut failed = false;
+    let mut number = None;
+    let mut vector = [0.0; 3];
+    for &(axis, scalar) in definition {
+      let part = match scalar.value.trim().parse::<f64>() {
+        Ok(number) => Ok(Value::Number(number)),
+        Err(_) => self.evaluate(&scalar.value),
+      };
+      let result = part.and_then(|part| match (part, axis) {
+        _ if !part.is_finite() => Err(Some(not_finite(&scalar.value))),
+        (_, None) => {
+          number = Some(part);
+          Ok(())
+        }
+        (Value::Number(part), Some(axis)) => {
+          vector[axis] = part;
+          Ok(())
+        }
+        (Value::Vector(_), Some(_)) => Err(Some("The axis of a vector must be a number".into())),
+      });
+      if let Err(message) = result {
+        failed = true;
+        if let Some(message) = message {
+          self.errors.push(Error::invalid(scalar.path.clone(), message));
+        }
+      }
+    }
+
+    if failed { None } else { Some(number.unwrap_or(Value::Vector(vector))) }
+  }
+
+  /// The value of a variable set to the expression `src`, or `None` if it failed. A variable
+  /// defined as a vector must be set to a vector.
+  fn evaluate_override(
+    &mut self,
+    name: &str,
+    src: &str,
+    definition: &[(Option<usize>, &Scalar)],
+  ) -> Option<Value> {
+    let message = match self.evaluate(src) {
+      Ok(Value::Number(_)) if definition.iter().any(|(axis, _)| axis.is_some()) => {
+        Some("The variable is a vector".to_string())
+      }
+      Ok(value) if value.is_finite() => return Some(value),
+      Ok(_) => Some(not_finite(src)),
+      Err(message) => message,
+    };
+    if let Some(message) = message {
+      let path = DocumentPath::root().key("variables").key(name);
+      self.errors.push(Error::invalid(path,
+        format!("Cannot set variable '{}' to '{}': {}", name, src, message)));
+    }
+    None
+  }
+}
+
+/// The value of an expression.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Value {
+  Number(f64),
+  Vector([f64; 3]),
+}
+
+impl Value {
+  fn is_finite(&self) -> bool {
+    match self {
+      Value::Number(number) => number.is_finite(),
+      Value::Vector(vector) => vector.iter().all(|it| it.is_finite()),
+    }
+  }
+
+  fn kind(&self) -> &'static str {
+    match self {
+      Value::Number(_) => "a number",
+      Value::Vector(_) => "a vector",
+    }
+  }
+}
+
+impl fmt::Display for Value {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Value::Number(number) => write!(f, "{}", number),
+      Value::Vector([x, y, z]) => write!(f, "{{ x: {}, y: {}, z: {} }}", x, y, z),
+    }
+  }
+}
+
+fn binary(operator: char, a: Value, b: Value) -> Result<Value, Option<String>> {
+  Ok(match (operator, a, b) {
+    (_, Value::Number(a), Value::Number(b)) => Value::Number(match operator {
+      '+' => a + b,
+      '-' => a - b,
+      '*' => a * b,
+      '/' => a / b,
+      '%' => a % b,
+      _ => a.powf(b),
+    }),
+    ('+', Value::Vector(a), Value::Vector(b)) => {
+      Value::Vector([a[0] + b[0], a[1] + b[1], a[2] + b[2]])
+    }
+    ('-', Value::Vector(a), Value::Vector(b)) => {
+      Value::Vector([a[0] - b[0], a[1] - b[1], a[2] - b[2]])
+    }
+    ('*', Value::Vector(v), Value::Number(n)) | ('*', Value::Number(n), Value::Vector(v)) => {
+      Value::Vector([v[0] * n, v[1] * n, v[2] * n])
+    }
+    ('/', Value::Vector(v), Value::Number(n)) => Value::Vector([v[0] / n, v[1] / n, v[2] / n]),
+    (_, a, b) => {
+      return Err(Some(format!("Cannot use '{}' with {} and {}", operator, a.kind(), b.kind())));
+    }
+  })
+}
+
+fn call(function: &str, arguments: &[f64]) -> Result<f64, Option<String>> {
+  let expected = match function {
+    "min" | "max" => 2,
+    _ => 1,
+  };
+  if arguments.len() != expected {
+    return Err(Some(format!("'{}' takes {} argument(s), found {}", function, expected,
+      arguments.len())));
+  }
+
+  let a = arguments[0];
+  Ok(match function {
+    "sin" => a.to_radians().sin(),
+    "cos" => a.to_radians().cos(),
+    "tan" => a.to_radians().tan(),
+    "asin" => a.asin().to_degrees(),
+    "acos" => a.acos().to_degrees(),
+    "atan" => a.atan().to_degrees(),
+    "sqrt" => a.sqrt(),
+    "abs" => a.abs(),
+    "floor" => a.floor(),
+    "ceil" => a.ceil(),
+    "round" => a.round(),
+    "min" => a.min(arguments[1]),
+    "max" => a.max(arguments[1]),
+    _ => unreachable!(),
+  })
+}
+
+const FUNCTIONS: [&str; 13] = [
+  "sin", "cos", "tan", "asin", "acos", "atan", "sqrt", "abs", "floor", "ceil", "round", "min",
+  "max",
+];
+
+enum Expression {
+  Number(f64),
+  Variable(String),
+  Negate(Box<Expression>),
+  Binary(char, Box<Expression>, Box<Expression>),
+  Call(String, Vec<Expression>),
+}
+
+/// A recursive descent parser for expressions. Operators bind as usual, with `^` binding tightest
+/// and to the right.
+struct ExpressionParser {
+  chars: Vec<char>,
+  position: usize,
+  /// How many `${` the parser is inside of, where names that are not functions are variables.
+  depth: usize,
+}
+
+impl ExpressionParser {
+  fn parse(src: &str) -> Result<Expression, String> {
+    let mut parser = ExpressionParser { chars: src.chars().collect(), position: 0, depth: 0 };
+    let expression = parser.sum()?;
+    match parser.peek() {
+      None => Ok(expression),
+      Some(c) => Err(parser.unexpected(c)),
+    }
+  }
+
+  /// The next character that is not whitespace.
+  fn peek(&mut self) -> Option<char> {
+    while self.chars.get(self.position).is_some_and(|it| it.is_whitespace()) {
+      self.position += 1;
+    }
+    self.chars.get(self.position).copied()
+  }
+
+  fn eat(&mut self, c: char) -> bool {
+    let found = self.peek() == Some(c);
+    if found {
+      self.position += 1;
+    }
+    found
+  }
+
+  fn expect(&mut self, c: char) -> Result<(), String> {
+    if self.eat(c) {
+      Ok(())
+    } else {
+      Err(match self.peek() {
+        Some(found) => format!("expected '{}' at character {}, found '{}'", c, self.position + 1,
+          found),
+        None => format!("expected '{}' at the end", c),
+      })
+    }
+  }
+
+  /// The name that starts at the current position, made of letters, digits and `_`.
+  fn name(&mut self) -> String {
+    let start = self.position;
+    while self.chars.get(self.position).is_some_and(|it| it.is_ascii_alphanumeric() || *it == '_') {
+      self.position += 1;
+    }
+    self.chars[start..self.position].iter().collect()
+  }
+
+  fn unexpected(&self, c: char) -> String {
+    format!("unexpected '{}' at character {}", c, self.position + 1)
+  }
+
+  fn sum(&mut self) -> Result<Expression, String> {
+    let mut expression = self.product()?;
+    while let Some(operator) = self.peek().filter(|it| *it == '+' || *it == '-') {
+      self.position += 1;
+      expression = Expression::Binary(operator, Box::new(expression), Box::new(self.product()?));
+    }
+    Ok(expression)
+  }
+
+  fn product(&mut self) -> Result<Expression, String> {
+    let mut expression = self.unary()?;
+    while let Some(operator) = self.peek().filter(|it| matches!(it, '*' | '/' | '%')) {
+      self.position += 1;
+      expression = Expression::Binary(operator, Box::new(expression), Box::new(self.unary()?));
+    }
+    Ok(expression)
+  }
+
+  fn unary(&mut self) -> Result<Expression, String> {
+    if self.eat('-') {
+      Ok(Expression::Negate(Box::new(self.unary()?)))
+    } else if self.eat('+') {
+      self.unary()
+    } else {
+      self.power()
+    }
+  }
+
+  fn power(&mut self) -> Result<Expression, String> {
+    let base = self.atom()?;
+    if self.eat('^') {
+      Ok(Expression::Binary('^', Box::new(base), Box::new(self.unary()?)))
+    } else {
+      Ok(base)
+    }
+  }
+
+  fn atom(&mut self) -> Result<Expression, String> {
+    let c = match self.peek() {
+      Some(c) => c,
+      None => return Err("expected a value at the end".to_string()),
+    };
+
+    if self.eat('(') {
+      let expression = self.sum()?;
+      self.expect(')')?;
+      Ok(expression)
+    } else if self.eat('$') {
+      self.expect('{')?;
+      self.depth += 1;
+      let expression = self.sum()?;
+      self.depth -= 1;
+      self.expect('}')?;
+      Ok(expression)
+    } else if c.is_ascii_digit() || c == '.' {
+      let start = self.position;
+      while self.chars.get(self.position).is_some_and(|it| it.is_ascii_digit() || *it == '.') {
+        self.position += 1;
+      }
+      // An exponent, as in `1e-3`, only when digits follow, so that `2e` stays an error.
+      if self.chars.get(self.position).is_some_and(|it| *it == 'e' || *it == 'E') {
+        let sign = matches!(self.chars.get(self.position + 1), Some('+' | '-')) as usize;
+        if self.chars.get(self.position + 1 + sign).is_some_and(|it| it.is_ascii_digit()) {
+          self.position += 1 + sign;
+          while self.chars.get(self.position).is_some_and(|it| it.is_ascii_digit()) {
+            self.position += 1;
+          }
+        }
+      }
+      let number: String = self.chars[start..self.position].iter().collect();
+      number.parse().map(Expression::Number).map_err(|_| format!("invalid number '{}'", number))
+    } else if c.is_ascii_alphabetic() || c == '_' {
+      let name = self.name();
+      if name == "pi" {
+        return Ok(Expression::Number(std::f64::consts::PI));
+      }
+      if !FUNCTIONS.contains(&name.as_str()) {
+        if self.depth == 0 {
+          return Err(format!("unknown name '{}'", name));
+        }
+        // The axis of a vector follows its name directly, as in `${offset.x}`.
+        if self.chars.get(self.position) == Some(&'.') {
+          self.position += 1;
+          return Ok(Expression::Variable(format!("{}.{}", name, self.name())));
+        }
+        return Ok(Expression::Variable(name));
+      }
+
+      self.expect('(')?;
+      let mut arguments = vec![self.sum()?];
+      while self.eat(',') {
+        arguments.push(self.sum()?);
+      }
+      self.expect(')')?;
+      Ok(Expression::Call(name, arguments))
+    } else {
+      Err(self.unexpected(c))
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn evaluate(src: &str) -> Result<Value, Option<String>> {
+    let mut variables = Variables {
+      definitions: HashMap::new(),
+      values: HashMap::new(),
+      overrides: HashMap::new(),
+      resolving: Vec::new(),
+      errors: Vec::new(),
+    };
+    variables.evaluate(src)
+  }
+
+  fn number(src: &str) -> f64 {
+    match evaluate(src) {
+      Ok(Value::Number(number)) => number,
+      result => panic!("'{}' gave {:?}", src, result),
+    }
+  }
+
+  fn message(src: &str) -> String {
+    match evaluate(src) {
+      Err(Some(message)) => message,
+      result => panic!("'{}' gave {:?}", src, result),
+    }
+  }
+
+  /// Substitute the expressions of a document and parse the result.
+  fn substitute_yaml(src: &str, overrides: &[(&str, &str)]) -> Result<serde_yaml::Value, String> {
+    let overrides: Vec<(String, String)> = overrides.iter()
+      .map(|(name, src)| (name.to_string(), src.to_string()))
+      .collect();
+    let substituted = substitute(src, &overrides).map_err(|it| it.to_string())?;
+    Ok(serde_yaml::from_str(&substituted.src).unwrap())
+  }
+
+  #[test]
+  fn operators_bind_by_precedence() {
+    assert_eq!(number("2 + 3 * 4 ^ 2 / 8"), 8.0);
+    assert_eq!(number("(2 + 3) * 4"), 20.0);
+    assert_eq!(number("10 - 4 - 3"), 3.0);
+    assert_eq!(number("7 % 4 * 2"), 6.0);
+    assert_eq!(number("2 ^ 3 ^ 2"), 512.0);
+  }
+
+  #[test]
+  fn unary_minus_binds_looser_than_powers() {
+    assert_eq!(number("-2 ^ 2"), -4.0);
+    assert_eq!(number("(-2) ^ 2"), 4.0);
+    assert_eq!(number("2 * -3"), -6.0);
+    assert_eq!(number("2 ^ -1"), 0.5);
+    assert_eq!(number("--1 + +2"), 3.0);
+  }
+
+  #[test]
+  fn numbers_can_have_exponents() {
+    assert_eq!(number("1e-3"), 0.001);
+    assert_eq!(number("2.5E+2 + 1e2"), 350.0);
+    assert_eq!(number("-1e1 ^ 2"), -100.0);
+    assert_eq!(message("2e"), "Invalid expression '2e': unexpected 'e' at character 2");
+  }
+
+  #[test]
+  fn functions_use_degrees() {
+    assert!((number("cos(60)") - 0.5).abs() < 1e-12);
+    assert!((number("atan(1)") - 45.0).abs() < 1e-12);
+    assert_eq!(number("max(1, min(5, 3)) + sqrt(16) + abs(-2) + floor(1.5)"), 10.0);
+    assert_eq!(number("pi"), std::f64::consts::PI);
+
+    assert_eq!(message("min(1)"), "'min' takes 2 argument(s), found 1");
+    assert!(message("foo(1)").ends_with("unknown name 'foo'"));
+    assert!(message("2 +").ends_with("expected a value at the end"));
+  }
+
+  #[test]
+  fn vectors_are_scaled_by_numbers() {
+    let doc = "
+variables:
+  v: { x: 1, y: 2, z: 3 }
+a: ${v * 2}
+b: ${0.5 * v - v}
+c: ${v / 2}
+d: ${v.y * 2}
+";
+    let value = substitute_yaml(doc, &[]).unwrap();
+    let vector = |key: &str| ["x", "y", "z"].map(|axis| value[key][axis].as_f64().unwrap());
+    assert_eq!(vector("a"), [2.0, 4.0, 6.0]);
+    assert_eq!(vector("b"), [-0.5, -1.0, -1.5]);
+    assert_eq!(vector("c"), [0.5, 1.0, 1.5]);
+    assert_eq!(value["d"].as_f64(), Some(4.0));
+
+    let err = substitute_yaml("variables: { v: { x: 1 } }\na: ${v * v}", &[]).unwrap_err();
+    assert!(err.ends_with("Cannot use '*' with a vector and a vector"), "{}", err);
+    let err = substitute_yaml("variables: { v: { x: 1 } }\na: ${v.w}", &[]).unwrap_err();
+    assert!(err.ends_with("Unknown axis 'w' of variable 'v'"), "{}", err);
+  }
+
+  #[test]
+  fn only_strings_with_a_marker_are_expressions() {
+    let value = substitute_yaml("name: 1/2\nlabel: 2 * cos(60)\nhalf: ${1/2}", &[]).unwrap();
+    assert_eq!(value["name"].as_str(), Some("1/2"));
+    assert_eq!(value["label"].as_str(), Some("2 * cos(60)"));
+    assert_eq!(value["half"].as_f64(), Some(0.5));
+  }
+
+  #[test]
+  fn variables_are_evaluated_when_used() {
+    let doc = "
+variables:
+  diameter: ${radius} * 2
+  radius: ${half_radius * 2}
+  half_radius: 1.5
+size: ${diameter}
+";
+    let value = substitute_yaml(doc, &[]).unwrap();
+    assert_eq!(value["size"].as_f64(), Some(6.0));
+    assert_eq!(value["variables"]["diameter"].as_f64(), Some(6.0));
+
+    let doc = "variables:\n  a: ${b}\n  b: ${c} + 1\n  c: ${a}\nsize: 1\n";
+    let err = substitute_yaml(doc, &[]).unwrap_err();
+    assert!(err.contains("depends on itself: "), "{}", err);
+    assert!(substitute_yaml("size: ${missing}", &[]).unwrap_err()
+      .ends_with("Unknown variable 'missing'"));
+  }
+
+  #[test]
+  fn overrides_replace_definitions() {
+    let doc = "variables:\n  a: 1\n  b: ${a} + 1\n  v: { x: 1 }\nsize: ${b}\n";
+    let value = substitute_yaml(doc, &[("a", "${2 * 5}")]).unwrap();
+    assert_eq!(value["variables"]["a"].as_f64(), Some(10.0));
+    assert_eq!(value["size"].as_f64(), Some(11.0));
+
+    let value = substitute_yaml(doc, &[("b", "${a} * 4")]).unwrap();
+    assert_eq!(value["size"].as_f64(), Some(4.0));
+
+    let err = substitute_yaml(doc, &[("c", "1")]).unwrap_err();
+    assert!(err.ends_with("Cannot set variable 'c', the model does not define it"), "{}", err);
+    let err = substitute_yaml(doc, &[("v", "1")]).unwrap_err();
+    assert!(err.ends_with("Cannot set variable 'v' to '1': The variable is a vector"), "{}", err);
+  }
+
+  #[test]
+  fn locations_after_longer_values_are_moved_back() {
+    let src = "variables: { a: 1 }\nx: [\"${a / 3}\", '${a}', bad]\n";
+    let substituted = substitute(src, &[]).unwrap();
+    let column = |line: &str, text: &str| line.find(text).unwrap() + 1;
+    let line = substituted.src.lines().nth(1).unwrap();
+    let original = src.lines().nth(1).unwrap();
+
+    let location = |line, column| substituted.original_location(Location { line, column }).column;
+    assert_eq!(location(2, column(line, "bad")), column(original, "bad"));
+    assert_eq!(location(2, column(line, "333")), 5);
+    assert_eq!(location(2, 2), 2);
+    assert_eq!(location(1, 12), 12);
+  }
+}
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 
+pub use array::*;
 pub use cone::*;
 pub use cube::*;
 pub use cylinder::*;
@@ -7,8 +8,9 @@ pub use deform::*;
 pub use icosphere::*;
 pub use uv_sphere::*;
 
-use crate::{GeometryBuffer, Vector3};
+use crate::{Axis, Color, GeometryBuffer, Rotation, Vector3};
 
+mod array;
 mod cone;
 mod cube;
 mod cylinder;
@@ -18,6 +20,7 @@ mod uv_sphere;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum Geometry {
+  Array(Box<Array>),
   Cone(Cone),
   Cube(Cube),
   Cylinder(Cylinder),
@@ -30,6 +33,7 @@ pub enum Geometry {
 
 impl GenerateGeometry for Geometry {
   fn generate_geometry(&self) -> GeometryBuffer {
+    let colors;
     let rotation;
     let scale;
     let translation;
@@ -37,9 +41,20 @@ impl GenerateGeometry for Geometry {
     let mut buf = GeometryBuffer::new();
 
     match self {
+      Geometry::Array(a) => {
+        buf = a.generate_geometry();
+
+        colors = None;
+
+        rotation = None;
+        scale = None;
+        translation = None;
+      }
       Geometry::Cone(c) => {
         buf = c.generate_geometry();
 
+        colors = c.colors.as_ref();
+
         rotation = c.rotation;
         scale = c.size;
         translation = c.position;
@@ -47,6 +62,8 @@ impl GenerateGeometry for Geometry {
       Geometry::Cube(b) => {
         buf = b.generate_geometry();
 
+        colors = b.colors.as_ref();
+
         rotation = b.rotation;
         scale = Some(b.size);
         translation = Some(b.position);
@@ -54,6 +71,8 @@ impl GenerateGeometry for Geometry {
       Geometry::Cylinder(c) => {
         buf = c.generate_geometry();
 
+        colors = c.colors.as_ref();
+
         rotation = c.rotation;
         scale = Some(c.size);
         translation = Some(c.position);
@@ -61,6 +80,8 @@ impl GenerateGeometry for Geometry {
       Geometry::Deform(i) => {
         buf = i.generate_geometry();
 
+        colors = None;
+
         rotation = None;
         scale = None;
         translation = None;
@@ -68,6 +89,8 @@ impl GenerateGeometry for Geometry {
       Geometry::Icosphere(i) => {
         buf = i.generate_geometry();
 
+        colors = i.colors.as_ref();
+
         rotation = i.rotation;
         scale = i.size;
         translation = i.position;
@@ -76,25 +99,30 @@ impl GenerateGeometry for Geometry {
         let min = p.position - p.size / Vector3::new(2.0, 2.0, 2.0);
         let max = min + p.size;
 
-        let v00 = buf.vertex(Vector3::new(min.x, 0.0, min.y));
-        let v01 = buf.vertex(Vector3::new(min.x, 0.0, max.y));
-        let v10 = buf.vertex(Vector3::new(max.x, 0.0, min.y));
-        let v11 = buf.vertex(Vector3::new(max.x, 0.0, max.y));
+        let v00 = buf.vertex_uv(Vector3::new(min.x, 0.0, min.y), [0.0, 0.0]);
+        let v01 = buf.vertex_uv(Vector3::new(min.x, 0.0, max.y), [0.0, 1.0]);
+        let v10 = buf.vertex_uv(Vector3::new(max.x, 0.0, min.y), [1.0, 0.0]);
+        let v11 = buf.vertex_uv(Vector3::new(max.x, 0.0, max.y), [1.0, 1.0]);
 
-        buf.triangle(v00, v10, v01);
-        buf.triangle(v01, v10, v11);
+        buf.triangle(v00, v01, v10);
+        buf.triangle(v01, v11, v10);
+
+        colors = p.colors.as_ref();
 
         rotation = p.rotation;
         scale = None;
         translation = None;
       }
       Geometry::Triangle(triangle) => {
-        let v0 = buf.vertex(triangle.points[0]);
-        let v1 = buf.vertex(triangle.points[1]);
-        let v2 = buf.vertex(triangle.points[2]);
+        let uvs = triangle.planar_uvs();
+        let v0 = buf.vertex_uv(triangle.points[0], uvs[0]);
+        let v1 = buf.vertex_uv(triangle.points[1], uvs[1]);
+        let v2 = buf.vertex_uv(triangle.points[2], uvs[2]);
 
         buf.triangle(v0, v1, v2);
 
+        colors = triangle.colors.as_ref();
+
         rotation = triangle.rotation;
         scale = None;
         translation = None;
@@ -102,6 +130,8 @@ impl GenerateGeometry for Geometry {
       Geometry::UvSphere(i) => {
         buf = i.generate_geometry();
 
+        colors = i.colors.as_ref();
+
         rotation = i.rotation;
         scale = i.size;
         translation = i.position;
@@ -113,17 +143,50 @@ impl GenerateGeometry for Geometry {
     }
 
     if let Some(rot) = rotation {
-      buf.rotate(rot);
+      buf.rotate(rot.to_quat());
     }
 
     if let Some(trans) = translation {
       buf.translate(trans);
     }
 
+    match colors {
+      Some(VertexColors::Solid(color)) => buf.set_colors(|_| color.rgba()),
+      Some(VertexColors::Faces(colors)) => {
+        buf.color_faces(&colors.iter().map(|it| it.rgba()).collect::<Vec<_>>());
+      }
+      Some(VertexColors::Gradient { axis, from, to }) => {
+        let min = buf.min_position().get_axis(*axis);
+        let max = buf.max_position().get_axis(*axis);
+        buf.set_colors(|it| {
+          let t = if max > min { (it.get_axis(*axis) - min) / (max - min) } else { 0.0 };
+          let (from, to) = (from.rgba(), to.rgba());
+          [0, 1, 2, 3].map(|i| from[i] + (to[i] - from[i]) * t)
+        });
+      }
+      None => {}
+    }
+
     buf
   }
 }
 
+/// How the vertices of a geometry entry are colored.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum VertexColors {
+  /// Every vertex has the same color.
+  Solid(Color),
+  /// Each face, made of the adjacent triangles lying in the same plane, takes the next color from
+  /// the list, starting over at the end of the list.
+  Faces(Vec<Color>),
+  /// The color blends from `from` at the lowest position along the axis to `to` at the highest.
+  Gradient {
+    axis: Axis,
+    from: Color,
+    to: Color,
+  },
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Plane {
   #[serde(default)]
@@ -131,14 +194,48 @@ pub struct Plane {
   #[serde(default)]
   pub size: Vector3,
   #[serde(default)]
-  pub rotation: Option<Vector3>,
+  pub rotation: Option<Rotation>,
+  /// Vertex colors, exported as `COLOR_0`.
+  #[serde(default)]
+  pub colors: Option<VertexColors>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Triangle {
   pub points: [Vector3; 3],
   #[serde(default)]
-  pub rotation: Option<Vector3>,
+  pub rotation: Option<Rotation>,
+  /// Vertex colors, exported as `COLOR_0`.
+  #[serde(default)]
+  pub colors: Option<VertexColors>,
+}
+
+impl Triangle {
+  /// Project the points onto the plane of the triangle, with u along the first edge, and scale
+  /// the result to fit the unit square.
+  fn planar_uvs(&self) -> [[f32; 2]; 3] {
+    let [p0, p1, p2] = self.points;
+    let tangent = (p1 - p0).normalize();
+    let bitangent = (p1 - p0).cross(p2 - p0).cross(tangent).normalize();
+
+    let projected = [
+      (0.0, 0.0),
+      ((p1 - p0).dot(tangent), (p1 - p0).dot(bitangent)),
+      ((p2 - p0).dot(tangent), (p2 - p0).dot(bitangent)),
+    ];
+
+    let min_u = projected.iter().map(|it| it.0).fold(f32::MAX, f32::min);
+    let min_v = projected.iter().map(|it| it.1).fold(f32::MAX, f32::min);
+    let max_u = projected.iter().map(|it| it.0).fold(f32::MIN, f32::max);
+    let max_v = projected.iter().map(|it| it.1).fold(f32::MIN, f32::max);
+    let extent = (max_u - min_u).max(max_v - min_v).max(f32::EPSILON);
+
+    let mut uvs = [[0.0; 2]; 3];
+    for (uv, (u, v)) in uvs.iter_mut().zip(projected.iter()) {
+      *uv = [(u - min_u) / extent, (max_v - v) / extent];
+    }
+    uvs
+  }
 }
 
 pub trait GenerateGeometry {
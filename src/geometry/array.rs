@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{ArrayPattern, GenerateGeometry, Geometry, GeometryBuffer};
+
+/// Repeats a geometry entry in a line, a grid or around an axis.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Array {
+  pub pattern: ArrayPattern,
+  pub geometry: Geometry,
+}
+
+impl GenerateGeometry for Array {
+  fn generate_geometry(&self) -> GeometryBuffer {
+    let original = self.geometry.generate_geometry();
+    let mut buf = GeometryBuffer::new();
+
+    for i in 0..self.pattern.count() {
+      let (scale, rotation, offset) = self.pattern.transform(i);
+      let mut copy = original.clone();
+      copy.scale(scale);
+      copy.rotate(rotation);
+      copy.translate(offset);
+      buf.append(&copy);
+    }
+
+    buf
+  }
+}
@@ -1,22 +1,26 @@
 use genmesh::generators::{IndexedPolygon, SharedVertex};
 use serde::{Deserialize, Serialize};
 
-use crate::{GenerateGeometry, GeometryBuffer, Vector3};
+use crate::{GenerateGeometry, GeometryBuffer, Rotation, Vector3, VertexColors};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Cone {
+  /// The number of sides around the base, at least 2.
   #[serde(default = "default_divides")]
   pub divides: u32,
   #[serde(default)]
   pub size: Option<Vector3>,
   #[serde(default)]
-  pub rotation: Option<Vector3>,
+  pub rotation: Option<Rotation>,
   #[serde(default)]
   pub position: Option<Vector3>,
+  /// Vertex colors, exported as `COLOR_0`.
+  #[serde(default)]
+  pub colors: Option<VertexColors>,
 }
 
 fn default_divides() -> u32 {
-  1
+  8
 }
 
 impl GenerateGeometry for Cone {
@@ -36,14 +40,15 @@ impl GenerateGeometry for Cone {
 
     for triangle in cone.indexed_polygon_iter() {
       buf.triangle(
-        triangle.x as u16,
-        triangle.y as u16,
-        triangle.z as u16,
+        triangle.x as u32,
+        triangle.y as u32,
+        triangle.z as u32,
       );
     }
 
+    buf.map_cylindrical_uvs(|it| (1.0 - it.z) / 2.0);
     buf.scale(Vector3::new(0.5, 0.5, 0.5));
-    buf.rotate(Vector3::new(0.0, 0.0, -90.0));
+    buf.rotate(glam::Quat::from_rotation_x((-90.0f32).to_radians()));
     buf
   }
 }
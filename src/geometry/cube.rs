@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::{GenerateGeometry, GeometryBuffer, Vector3};
+use crate::{GenerateGeometry, GeometryBuffer, Rotation, Vector3, VertexColors};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Cube {
@@ -9,9 +9,12 @@ pub struct Cube {
   #[serde(default)]
   pub size: Vector3,
   #[serde(default)]
-  pub rotation: Option<Vector3>,
+  pub rotation: Option<Rotation>,
   #[serde(default)]
   pub offsets: Option<CubeVertexOffset>,
+  /// Vertex colors, exported as `COLOR_0`.
+  #[serde(default)]
+  pub colors: Option<VertexColors>,
 }
 
 #[derive(Copy, Clone, Debug, Default, Deserialize, Serialize)]
@@ -44,38 +47,42 @@ impl GenerateGeometry for Cube {
     let offset = self.offsets.unwrap_or_default();
     let os = Vector3::ONE / self.size;
 
-    let p000 = buf.vertex(min + offset.v000);
-    let p001 = buf.vertex(Vector3::new(min.x, min.y, max.z) + offset.v001 * os);
-    let p010 = buf.vertex(Vector3::new(min.x, max.y, min.z) + offset.v010 * os);
-    let p011 = buf.vertex(Vector3::new(min.x, max.y, max.z) + offset.v011 * os);
-    let p100 = buf.vertex(Vector3::new(max.x, min.y, min.z) + offset.v100 * os);
-    let p101 = buf.vertex(Vector3::new(max.x, min.y, max.z) + offset.v101 * os);
-    let p110 = buf.vertex(Vector3::new(max.x, max.y, min.z) + offset.v110 * os);
-    let p111 = buf.vertex(Vector3::new(max.x, max.y, max.z) + offset.v111 * os);
-
-    // back
-    buf.triangle(p000, p010, p100);
-    buf.triangle(p010, p110, p100);
-
-    // front
-    buf.triangle(p001, p101, p011);
-    buf.triangle(p011, p101, p111);
-
-    // bottom
-    buf.triangle(p000, p100, p001);
-    buf.triangle(p100, p101, p001);
+    let p000 = min + offset.v000 * os;
+    let p001 = Vector3::new(min.x, min.y, max.z) + offset.v001 * os;
+    let p010 = Vector3::new(min.x, max.y, min.z) + offset.v010 * os;
+    let p011 = Vector3::new(min.x, max.y, max.z) + offset.v011 * os;
+    let p100 = Vector3::new(max.x, min.y, min.z) + offset.v100 * os;
+    let p101 = Vector3::new(max.x, min.y, max.z) + offset.v101 * os;
+    let p110 = Vector3::new(max.x, max.y, min.z) + offset.v110 * os;
+    let p111 = Vector3::new(max.x, max.y, max.z) + offset.v111 * os;
 
-    // top
-    buf.triangle(p010, p011, p110);
-    buf.triangle(p110, p011, p111);
+    // Every face has its own vertices so that it can be box mapped with the full texture. The
+    // corners of each face are listed counter-clockwise as seen from outside the cube, starting
+    // from the bottom left of the texture.
+    let faces = [
+      // back
+      [p100, p000, p010, p110],
+      // front
+      [p001, p101, p111, p011],
+      // bottom
+      [p000, p100, p101, p001],
+      // top
+      [p011, p111, p110, p010],
+      // left
+      [p000, p001, p011, p010],
+      // right
+      [p101, p100, p110, p111],
+    ];
 
-    // left
-    buf.triangle(p000, p001, p010);
-    buf.triangle(p001, p011, p010);
+    for face in &faces {
+      let v0 = buf.vertex_uv(face[0], [0.0, 1.0]);
+      let v1 = buf.vertex_uv(face[1], [1.0, 1.0]);
+      let v2 = buf.vertex_uv(face[2], [1.0, 0.0]);
+      let v3 = buf.vertex_uv(face[3], [0.0, 0.0]);
 
-    // right
-    buf.triangle(p100, p110, p101);
-    buf.triangle(p101, p110, p111);
+      buf.triangle(v0, v1, v2);
+      buf.triangle(v0, v2, v3);
+    }
 
     buf
   }
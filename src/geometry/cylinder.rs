@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::{GenerateGeometry, GeometryBuffer, Vector3};
+use crate::{GenerateGeometry, GeometryBuffer, Rotation, Vector3, VertexColors};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Cylinder {
@@ -10,7 +10,10 @@ pub struct Cylinder {
   pub size: Vector3,
   pub points: u32,
   #[serde(default)]
-  pub rotation: Option<Vector3>,
+  pub rotation: Option<Rotation>,
+  /// Vertex colors, exported as `COLOR_0`.
+  #[serde(default)]
+  pub colors: Option<VertexColors>,
 }
 
 impl GenerateGeometry for Cylinder {
@@ -28,10 +31,12 @@ impl GenerateGeometry for Cylinder {
     let z0 = -0.5;
     let z1 = 0.5;
 
+    // The caps are planar mapped and the sides are mapped around the axis, so they do not share
+    // vertices.
     let center_x = 0.0;
     let center_y = 0.0;
-    let center_z0 = buf.vertex(Vector3::new(center_x, center_y, z0));
-    let center_z1 = buf.vertex(Vector3::new(center_x, center_y, z1));
+    let center_z0 = buf.vertex_uv(Vector3::new(center_x, center_y, z0), [0.5, 0.5]);
+    let center_z1 = buf.vertex_uv(Vector3::new(center_x, center_y, z1), [0.5, 0.5]);
 
     let mut indices = Vec::new();
     for i in 0..self.points {
@@ -39,8 +44,8 @@ impl GenerateGeometry for Cylinder {
       let x0 = radians.cos() * 0.5;
       let y0 = radians.sin() * 0.5;
 
-      let v000 = buf.vertex(Vector3::new(x0, y0, z0));
-      let v001 = buf.vertex(Vector3::new(x0, y0, z1));
+      let v000 = buf.vertex_uv(Vector3::new(x0, y0, z0), [0.5 - x0, 0.5 - y0]);
+      let v001 = buf.vertex_uv(Vector3::new(x0, y0, z1), [0.5 + x0, 0.5 - y0]);
       indices.push(v000);
       indices.push(v001);
     }
@@ -51,14 +56,35 @@ impl GenerateGeometry for Cylinder {
       let v110 = indices[2 * ((i as usize + 1) % self.points as usize)];
       let v111 = indices[2 * ((i as usize + 1) % self.points as usize) + 1];
 
-      buf.triangle(v000, v110, center_z0);
+      buf.triangle(v000, center_z0, v110);
       buf.triangle(v001, v111, center_z1);
+    }
+
+    let mut sides = GeometryBuffer::new();
+    let mut side_indices = Vec::new();
+    for i in 0..self.points {
+      let radians = calculate_angle(self.points, i);
+      let x0 = radians.cos() * 0.5;
+      let y0 = radians.sin() * 0.5;
+
+      side_indices.push(sides.vertex(Vector3::new(x0, y0, z0)));
+      side_indices.push(sides.vertex(Vector3::new(x0, y0, z1)));
+    }
 
-      buf.triangle(v000, v110, v111);
-      buf.triangle(v000, v001, v111);
+    for i in 0..self.points {
+      let v000 = side_indices[2 * i as usize];
+      let v001 = side_indices[2 * i as usize + 1];
+      let v110 = side_indices[2 * ((i as usize + 1) % self.points as usize)];
+      let v111 = side_indices[2 * ((i as usize + 1) % self.points as usize) + 1];
+
+      sides.triangle(v000, v110, v111);
+      sides.triangle(v000, v111, v001);
     }
 
-    buf.rotate(Vector3::new(0.0, 0.0, 90.0));
+    sides.map_cylindrical_uvs(|it| z1 - it.z);
+    buf.append(&sides);
+
+    buf.rotate(glam::Quat::from_rotation_x(90.0f32.to_radians()));
     buf
   }
 }
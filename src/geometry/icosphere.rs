@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::{GeometryBuffer, Vector3, GenerateGeometry};
+use crate::{GenerateGeometry, GeometryBuffer, Rotation, Vector3, VertexColors};
 use genmesh::generators::{IndexedPolygon, SharedVertex};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -10,9 +10,12 @@ pub struct Icosphere {
   #[serde(default)]
   pub size: Option<Vector3>,
   #[serde(default)]
-  pub rotation: Option<Vector3>,
+  pub rotation: Option<Rotation>,
   #[serde(default)]
   pub position: Option<Vector3>,
+  /// Vertex colors, exported as `COLOR_0`.
+  #[serde(default)]
+  pub colors: Option<VertexColors>,
 }
 
 fn default_divides() -> u32 {
@@ -35,14 +38,15 @@ impl GenerateGeometry for Icosphere {
 
     for triangle in icosphere.indexed_polygon_iter() {
       buf.triangle(
-        triangle.x as u16,
-        triangle.y as u16,
-        triangle.z as u16,
+        triangle.x as u32,
+        triangle.y as u32,
+        triangle.z as u32,
       );
     }
 
+    buf.map_cylindrical_uvs(|it| it.z.clamp(-1.0, 1.0).acos() / std::f32::consts::PI);
     buf.scale(Vector3::new(0.5, 0.5, 0.5));
-    buf.rotate(Vector3::new(0.0, 0.0, 90.0));
+    buf.rotate(glam::Quat::from_rotation_x(90.0f32.to_radians()));
     buf
   }
 }
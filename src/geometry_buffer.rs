@@ -1,19 +1,53 @@
+use std::collections::HashMap;
+
 use rand::prelude::*;
 use rand_pcg::Pcg64;
 
 use crate::Vector3;
 
+/// Adjacent faces meeting at a sharper angle than this keep separate normals in `smooth_normals`.
+const CREASE_ANGLE: f32 = 60.0;
+
 #[derive(Copy, Clone, Debug, Default)]
 #[repr(C)]
-pub struct Triangle(pub [u16; 3]);
+pub struct Triangle(pub [u32; 3]);
 
 #[derive(Clone, Debug, Default)]
 pub struct GeometryBuffer {
   pub vertices: Vec<Vector3>,
+  /// Texture coordinates, one per vertex.
+  pub uvs: Vec<[f32; 2]>,
+  /// Linear RGBA vertex colors, either empty or one per vertex.
+  pub colors: Vec<[f32; 4]>,
   pub triangles: Vec<Triangle>,
 }
 
+const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
 impl GeometryBuffer {
+  /// Append the vertices and triangles of another buffer to this one.
+  pub fn append(&mut self, other: &GeometryBuffer) {
+    let offset = self.vertices.len() as u32;
+    if self.colors.is_empty() && !other.colors.is_empty() {
+      self.colors.resize(self.vertices.len(), WHITE);
+    }
+    self.vertices.extend_from_slice(&other.vertices);
+    self.uvs.extend_from_slice(&other.uvs);
+    if other.colors.is_empty() && !self.colors.is_empty() {
+      self.colors.resize(self.vertices.len(), WHITE);
+    } else {
+      self.colors.extend_from_slice(&other.colors);
+    }
+
+    for triangle in &other.triangles {
+      self.triangles.push(Triangle([
+        triangle.0[0] + offset,
+        triangle.0[1] + offset,
+        triangle.0[2] + offset,
+      ]));
+    }
+  }
+
   pub fn apply_transform(&mut self, matrix: glam::Mat4) {
     for i in 0..(self.vertices.len()) {
       let v0 = self.vertices[i];
@@ -23,10 +57,18 @@ impl GeometryBuffer {
     }
   }
 
+  /// Move every vertex by a random offset. Vertices sharing a position are moved together, so
+  /// that seams between them stay closed.
   pub fn deform(&mut self, min: Vector3, max: Vector3, scale: Vector3, seed: u64) {
     let mut rng = Pcg64::seed_from_u64(seed);
+    let mut offsets = HashMap::new();
 
     for i in 0..self.vertices.len() {
+      if let Some(offset) = offsets.get(&position_key(self.vertices[i])) {
+        self.vertices[i] += *offset;
+        continue;
+      }
+
       let min_x = min.x * scale.x;
       let x_range = (max.x * scale.y) - min_x;
       let x_offset = min_x + rng.gen::<f32>() * x_range;
@@ -39,8 +81,260 @@ impl GeometryBuffer {
       let z_range = (max.z * scale.z) - min_z;
       let z_offset = min_z + rng.gen::<f32>() * z_range;
 
-      self.vertices[i] += Vector3::new(x_offset, y_offset, z_offset);
+      let offset = Vector3::new(x_offset, y_offset, z_offset);
+      offsets.insert(position_key(self.vertices[i]), offset);
+      self.vertices[i] += offset;
+    }
+  }
+
+  /// Calculate the normal of every triangle, in the same order as `triangles`.
+  pub fn face_normals(&self) -> Vec<Vector3> {
+    self.triangles.iter()
+      .map(|triangle| {
+        let v0 = self.vertices[triangle.0[0] as usize];
+        let v1 = self.vertices[triangle.0[1] as usize];
+        let v2 = self.vertices[triangle.0[2] as usize];
+        (v1 - v0).cross(v2 - v0).normalize()
+      })
+      .collect()
+  }
+
+  /// Transform the face normals into a non-indexed array matching `make_redundant`.
+  pub fn make_redundant_normals(&self) -> Vec<Vector3> {
+    let mut output = Vec::new();
+    for normal in self.face_normals() {
+      output.push(normal);
+      output.push(normal);
+      output.push(normal);
+    }
+    output
+  }
+
+  /// Calculate smooth per-vertex normals by averaging the area-weighted normals of every triangle
+  /// sharing the vertex. Separate vertices at the same position are smoothed together unless
+  /// their faces meet at an angle sharper than `CREASE_ANGLE`, so that texture seams stay smooth
+  /// while hard edges stay hard.
+  pub fn smooth_normals(&self) -> Vec<Vector3> {
+    let mut vertex_normals = vec![Vector3::ZERO; self.vertices.len()];
+    for triangle in &self.triangles {
+      let v0 = self.vertices[triangle.0[0] as usize];
+      let v1 = self.vertices[triangle.0[1] as usize];
+      let v2 = self.vertices[triangle.0[2] as usize];
+      let normal = (v1 - v0).cross(v2 - v0);
+
+      for index in &triangle.0 {
+        vertex_normals[*index as usize] += normal;
+      }
+    }
+
+    let mut shared_positions: HashMap<[u32; 3], Vec<usize>> = HashMap::new();
+    for (i, vertex) in self.vertices.iter().enumerate() {
+      shared_positions.entry(position_key(*vertex)).or_default().push(i);
+    }
+
+    let min_dot = CREASE_ANGLE.to_radians().cos();
+    let mut output = Vec::with_capacity(self.vertices.len());
+    for (i, vertex) in self.vertices.iter().enumerate() {
+      let own = vertex_normals[i].normalize();
+      let mut sum = Vector3::ZERO;
+      for j in &shared_positions[&position_key(*vertex)] {
+        if *j == i || vertex_normals[*j].normalize().dot(own) >= min_dot {
+          sum += vertex_normals[*j];
+        }
+      }
+      output.push(sum.normalize());
+    }
+    output
+  }
+
+  /// Transform the texture coordinates into a non-indexed array matching `make_redundant`.
+  pub fn make_redundant_uvs(&self) -> Vec<[f32; 2]> {
+    let mut output = Vec::new();
+    for triangle in &self.triangles {
+      for index in &triangle.0 {
+        output.push(self.uvs[*index as usize]);
+      }
+    }
+    output
+  }
+
+  /// Assign texture coordinates wrapping around the z axis, with u following the angle around the
+  /// axis and v given by `v`. Where u wraps around from 1 to 0, and on the axis itself where u is
+  /// centered on each triangle, vertices keep the coordinates of the first triangle that uses
+  /// them and are copied for the others. Copies are added after the existing vertices.
+  pub fn map_cylindrical_uvs(&mut self, v: impl Fn(Vector3) -> f32) {
+    let on_axis = |it: Vector3| it.x * it.x + it.y * it.y < 1e-10;
+
+    self.uvs = self.vertices.iter()
+      .map(|it| {
+        let u = (it.y.atan2(it.x) / (2.0 * std::f32::consts::PI)).rem_euclid(1.0);
+        [u, v(*it)]
+      })
+      .collect();
+    let uvs = self.uvs.clone();
+
+    // Triangles across the seam add 1 to the u of their vertices below 0.5. Vertices that only
+    // such triangles use are moved over the seam instead of copied.
+    let wraps: Vec<bool> = self.triangles.iter()
+      .map(|triangle| {
+        let us = triangle.0.iter()
+          .filter(|it| !on_axis(self.vertices[**it as usize]))
+          .map(|it| uvs[*it as usize][0]);
+        let (min, max) = us.fold((f32::MAX, f32::MIN), |(min, max), u| (min.min(u), max.max(u)));
+        max - min > 0.5
+      })
+      .collect();
+    let mut unwrapped = vec![false; self.vertices.len()];
+    for (triangle, _) in self.triangles.iter().zip(&wraps).filter(|(_, wraps)| !**wraps) {
+      for index in &triangle.0 {
+        unwrapped[*index as usize] = true;
+      }
     }
+
+    let mut wrapped = HashMap::new();
+    let mut centered = HashMap::new();
+    let mut centered_originals = Vec::new();
+    for (t, wraps) in wraps.into_iter().enumerate() {
+      let indices = self.triangles[t].0;
+      let (mut u_sum, mut count) = (0.0, 0);
+      for (corner, index) in indices.iter().copied().enumerate() {
+        if on_axis(self.vertices[index as usize]) {
+          continue;
+        }
+
+        let uv = uvs[index as usize];
+        if wraps && uv[0] < 0.5 {
+          let duplicate = *wrapped.entry(index).or_insert_with(|| {
+            if unwrapped[index as usize] {
+              self.vertex_uv(self.vertices[index as usize], [uv[0] + 1.0, uv[1]])
+            } else {
+              self.uvs[index as usize][0] = uv[0] + 1.0;
+              index
+            }
+          });
+          self.triangles[t].0[corner] = duplicate;
+        }
+        u_sum += self.uvs[self.triangles[t].0[corner] as usize][0];
+        count += 1;
+      }
+
+      if count < 3 {
+        let u = if count == 0 { 0.0 } else { u_sum / count as f32 };
+        for (corner, index) in indices.iter().copied().enumerate() {
+          if on_axis(self.vertices[index as usize]) {
+            let duplicate = *centered.entry((index, u.to_bits())).or_insert_with(|| {
+              if centered_originals.contains(&index) {
+                let uv = self.uvs[index as usize];
+                self.vertex_uv(self.vertices[index as usize], [u, uv[1]])
+              } else {
+                centered_originals.push(index);
+                self.uvs[index as usize][0] = u;
+                index
+              }
+            });
+            self.triangles[t].0[corner] = duplicate;
+          }
+        }
+      }
+    }
+  }
+
+  /// Transform the vertex colors into a non-indexed array matching `make_redundant`.
+  pub fn make_redundant_colors(&self) -> Vec<[f32; 4]> {
+    let mut output = Vec::new();
+    for triangle in &self.triangles {
+      for index in &triangle.0 {
+        output.push(self.colors[*index as usize]);
+      }
+    }
+    output
+  }
+
+  /// Set the color of every vertex from its position.
+  pub fn set_colors(&mut self, color: impl Fn(Vector3) -> [f32; 4]) {
+    self.colors = self.vertices.iter().map(|it| color(*it)).collect();
+  }
+
+  /// Color every face returned by `planar_faces` with the next color in `colors`, starting over
+  /// at the end of the list. Vertices shared by faces of different colors are duplicated.
+  pub fn color_faces(&mut self, colors: &[[f32; 4]]) {
+    if colors.is_empty() {
+      return;
+    }
+
+    let faces = self.planar_faces();
+    self.colors = vec![WHITE; self.vertices.len()];
+
+    let mut owners = HashMap::new();
+    let mut duplicates = HashMap::new();
+    for (t, face) in faces.into_iter().enumerate() {
+      let color = colors[face % colors.len()];
+
+      for corner in 0..3 {
+        let index = self.triangles[t].0[corner];
+        match owners.get(&index) {
+          None => {
+            owners.insert(index, face);
+            self.colors[index as usize] = color;
+          }
+          Some(owner) if *owner == face => {}
+          Some(_) => {
+            let duplicate = *duplicates.entry((index, face)).or_insert_with(|| {
+              let (position, uv) = (self.vertices[index as usize], self.uvs[index as usize]);
+              let duplicate = self.vertex_uv(position, uv);
+              self.colors[duplicate as usize] = color;
+              duplicate
+            });
+            self.triangles[t].0[corner] = duplicate;
+          }
+        }
+      }
+    }
+  }
+
+  /// Group the triangles into faces, where a face is made of adjacent triangles lying in the same
+  /// plane. Returns the face of every triangle, with faces numbered in the order of their first
+  /// triangle.
+  pub fn planar_faces(&self) -> Vec<usize> {
+    fn find(parents: &mut [usize], mut i: usize) -> usize {
+      while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+      }
+      i
+    }
+
+    let normals = self.face_normals();
+    let mut parents: Vec<usize> = (0..self.triangles.len()).collect();
+    let mut edges: HashMap<([u32; 3], [u32; 3]), usize> = HashMap::new();
+
+    for (t, triangle) in self.triangles.iter().enumerate() {
+      for corner in 0..3 {
+        let a = position_key(self.vertices[triangle.0[corner] as usize]);
+        let b = position_key(self.vertices[triangle.0[(corner + 1) % 3] as usize]);
+        let edge = if a < b { (a, b) } else { (b, a) };
+
+        match edges.get(&edge) {
+          Some(other) if normals[*other].dot(normals[t]) > 0.9999 => {
+            let (root, other_root) = (find(&mut parents, t), find(&mut parents, *other));
+            parents[root.max(other_root)] = root.min(other_root);
+          }
+          Some(_) => {}
+          None => {
+            edges.insert(edge, t);
+          }
+        }
+      }
+    }
+
+    let mut face_ids = HashMap::new();
+    (0..self.triangles.len())
+      .map(|t| {
+        let root = find(&mut parents, t);
+        let next_id = face_ids.len();
+        *face_ids.entry(root).or_insert(next_id)
+      })
+      .collect()
   }
 
   /// Transform the geometry buffer into a non-indexed array of vertices.
@@ -61,7 +355,7 @@ impl GeometryBuffer {
   }
 
   pub fn max_position(&self) -> Vector3 {
-    let mut max = Vector3::new(std::f32::MIN, std::f32::MIN, std::f32::MIN);
+    let mut max = Vector3::new(f32::MIN, f32::MIN, f32::MIN);
     for vertex in &self.vertices {
       max.x = max.x.max(vertex.x);
       max.y = max.y.max(vertex.y);
@@ -71,7 +365,7 @@ impl GeometryBuffer {
   }
 
   pub fn min_position(&self) -> Vector3 {
-    let mut min = Vector3::new(std::f32::MAX, std::f32::MAX, std::f32::MAX);
+    let mut min = Vector3::new(f32::MAX, f32::MAX, f32::MAX);
     for vertex in &self.vertices {
       min.x = min.x.min(vertex.x);
       min.y = min.y.min(vertex.y);
@@ -88,9 +382,8 @@ impl GeometryBuffer {
     self.triangles.remove(index);
   }
 
-  pub fn rotate(&mut self, rot: Vector3) {
-    let matrix = glam::Mat4::from_euler(glam::EulerRot::ZYX,
-      rot.x.to_radians(), rot.y.to_radians(), rot.z.to_radians());
+  pub fn rotate(&mut self, rotation: glam::Quat) {
+    let matrix = glam::Mat4::from_quat(rotation);
 
     self.apply_transform(matrix);
   }
@@ -103,7 +396,7 @@ impl GeometryBuffer {
     self.apply_transform(matrix);
   }
 
-  pub fn split_triangle(&mut self, index: usize, p_index: u16) {
+  pub fn split_triangle(&mut self, index: usize, p_index: u32) {
     let triangle = self.triangles[index];
 
     self.triangles.remove(index);
@@ -120,23 +413,153 @@ impl GeometryBuffer {
     self.apply_transform(matrix);
   }
 
-  pub fn triangle(&mut self, a: u16, b: u16, c: u16) {
-    if a == b || b == c || c == a {
-      panic!(format!("Cannot add triangle with duplicate indices {}, {}, {}", a, b, c));
+  pub fn triangle(&mut self, a: u32, b: u32, c: u32) {
+    self.triangles.push(Triangle([a, b, c]));
+  }
+
+  /// Check that every triangle refers to three distinct, existing vertices, and that colors, if
+  /// there are any, are given for every vertex.
+  pub fn validate(&self) -> Result<(), String> {
+    let max_vertices = u32::MAX as usize;
+    if self.vertices.len() >= max_vertices {
+      return Err(format!("Too many vertices n={} (max is {}).", self.vertices.len(), max_vertices));
     }
 
-    self.triangles.push(Triangle([a, b, c]));
+    if !self.colors.is_empty() && self.colors.len() != self.vertices.len() {
+      return Err(format!("Found {} vertex colors for {} vertices", self.colors.len(),
+        self.vertices.len()));
+    }
+
+    for triangle in &self.triangles {
+      let [a, b, c] = triangle.0;
+      if a == b || b == c || c == a {
+        return Err(format!("Cannot add triangle with duplicate indices {}, {}, {}", a, b, c));
+      }
+
+      if let Some(index) = triangle.0.iter().find(|it| **it as usize >= self.vertices.len()) {
+        return Err(format!("Triangle refers to vertex {}, but there are only {} vertices", index,
+          self.vertices.len()));
+      }
+    }
+
+    Ok(())
   }
 
-  pub fn vertex(&mut self, pos: Vector3) -> u16 {
+  pub fn vertex(&mut self, pos: Vector3) -> u32 {
+    self.vertex_uv(pos, [0.0, 0.0])
+  }
+
+  pub fn vertex_uv(&mut self, pos: Vector3, uv: [f32; 2]) -> u32 {
     let index = self.vertices.len();
     self.vertices.push(pos);
+    self.uvs.push(uv);
+    if !self.colors.is_empty() {
+      self.colors.push(WHITE);
+    }
+    index as u32
+  }
+}
+
+/// A hashable key for a position, treating positive and negative zero as equal.
+fn position_key(pos: Vector3) -> [u32; 3] {
+  [(pos.x + 0.0).to_bits(), (pos.y + 0.0).to_bits(), (pos.z + 0.0).to_bits()]
+}
 
-    let max_index = u16::max_value() as usize;
-    if index >= max_index {
-      panic!(format!("Too many vertices n={} (max is {}).", index, max_index));
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{GenerateGeometry, Geometry};
+
+  fn generate(src: &str) -> GeometryBuffer {
+    serde_yaml::from_str::<Geometry>(src).unwrap().generate_geometry()
+  }
+
+  #[test]
+  fn cylindrical_uvs_leave_no_unused_vertices() {
+    for src in ["Cone: { divides: 8 }", "UvSphere: { u: 8, v: 6 }", "Icosphere: { divides: 1 }"] {
+      let buf = generate(src);
+      let mut used = vec![false; buf.vertices.len()];
+      for index in buf.triangles.iter().flat_map(|it| it.0.iter()) {
+        used[*index as usize] = true;
+      }
+      assert!(used.iter().all(|it| *it), "{}", src);
+      assert_eq!(buf.uvs.len(), buf.vertices.len());
+      for normal in buf.smooth_normals() {
+        assert!((normal.dot(normal) - 1.0).abs() < 1e-4, "{}: {:?}", src, normal);
+      }
     }
+  }
+
+  #[test]
+  fn seam_vertices_are_only_copied_when_shared() {
+    let mut buf = GeometryBuffer::new();
+    let (sin, cos) = 10f32.to_radians().sin_cos();
+    let a = buf.vertex(Vector3::new(cos, -sin, 0.0));
+    let b = buf.vertex(Vector3::new(cos, sin, 0.0));
+    let c = buf.vertex(Vector3::new(0.0, 1.0, 0.0));
+    let pole = buf.vertex(Vector3::new(0.0, 0.0, 1.0));
+    buf.triangle(a, b, pole);
+    buf.map_cylindrical_uvs(|it| it.z);
+
+    // The only triangle crosses the seam, so its vertices keep their index.
+    assert_eq!(buf.vertices.len(), 4);
+    assert_eq!(buf.triangles[0].0, [a, b, pole]);
+    assert!((buf.uvs[b as usize][0] - (1.0 + 10.0 / 360.0)).abs() < 1e-5);
+    assert!((buf.uvs[pole as usize][0] - 1.0).abs() < 1e-5);
 
-    index as u16
+    // Another triangle on the near side of the seam needs the vertex as it was.
+    buf.triangle(b, c, pole);
+    buf.map_cylindrical_uvs(|it| it.z);
+    assert_eq!(buf.vertices.len(), 6);
+    assert_eq!(buf.triangles[0].0, [a, 4, pole]);
+    assert_eq!(buf.triangles[1].0, [b, c, 5]);
+    assert!((buf.uvs[b as usize][0] - 10.0 / 360.0).abs() < 1e-5);
+  }
+
+  #[test]
+  fn validate_rejects_broken_triangles_and_colors() {
+    let mut buf = GeometryBuffer::new();
+    for x in 0..3 {
+      buf.vertex(Vector3::new(x as f32, 0.0, 0.0));
+    }
+    buf.triangle(0, 1, 2);
+    assert_eq!(buf.validate(), Ok(()));
+
+    buf.colors = vec![WHITE; 2];
+    assert_eq!(buf.validate().unwrap_err(), "Found 2 vertex colors for 3 vertices");
+    buf.set_colors(|_| WHITE);
+    assert_eq!(buf.validate(), Ok(()));
+
+    buf.triangles[0] = Triangle([0, 2, 2]);
+    assert_eq!(buf.validate().unwrap_err(), "Cannot add triangle with duplicate indices 0, 2, 2");
+    buf.triangles[0] = Triangle([0, 1, 3]);
+    assert_eq!(buf.validate().unwrap_err(),
+      "Triangle refers to vertex 3, but there are only 3 vertices");
+  }
+
+  #[test]
+  fn appended_buffers_keep_a_color_per_vertex() {
+    let mut buf = generate("Triangle: { points: [{ x: 1 }, { y: 1 }, {}] }");
+    buf.append(&generate("Triangle: { points: [{ x: 1 }, { y: 1 }, {}], colors: { Solid: red } }"));
+    buf.append(&generate("Triangle: { points: [{ x: 1 }, { y: 1 }, {}] }"));
+    assert_eq!(buf.validate(), Ok(()));
+    let red = [1.0, 0.0, 0.0, 1.0];
+    assert_eq!(buf.colors, [WHITE, WHITE, WHITE, red, red, red, WHITE, WHITE, WHITE]);
+  }
+
+  #[test]
+  fn every_face_gets_a_single_color() {
+    let buf = generate(
+      "Cube: { size: { x: 1, y: 1, z: 1 }, colors: { Faces: [red, lime, blue] } }");
+    assert_eq!(buf.validate(), Ok(()));
+
+    let faces = buf.planar_faces();
+    assert_eq!(faces.iter().max(), Some(&5));
+    let colors = [[1.0, 0.0, 0.0, 1.0], [0.0, 1.0, 0.0, 1.0], [0.0, 0.0, 1.0, 1.0]];
+    for (triangle, face) in buf.triangles.iter().zip(faces) {
+      for index in triangle.0 {
+        assert_eq!(buf.colors[index as usize], colors[face % 3]);
+      }
+    }
   }
 }
@@ -16,18 +16,48 @@ pub struct Gltf {
   #[serde(skip_serializing_if = "Vec::is_empty")]
   pub buffers: Vec<Buffer>,
   #[serde(rename = "bufferViews")]
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
   pub buffer_views: Vec<BufferView>,
   #[serde(skip_serializing_if = "Vec::is_empty")]
   pub accessors: Vec<Accessor>,
   #[serde(skip_serializing_if = "Vec::is_empty")]
   pub materials: Vec<Material>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub textures: Vec<Texture>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub images: Vec<Image>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub samplers: Vec<TextureSampler>,
   #[serde(skip_serializing_if = "Vec::is_empty")]
   pub animations: Vec<Animation>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub skins: Vec<Skin>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub cameras: Vec<Camera>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<Extensions>,
+  #[serde(rename = "extensionsUsed")]
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub extensions_used: Vec<String>,
   pub asset: Asset,
 }
 
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Extensions {
+  #[serde(rename = "KHR_lights_punctual")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub lights_punctual: Option<LightsPunctual>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LightsPunctual {
+  pub lights: Vec<Light>,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Scene {
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub name: Option<String>,
   pub nodes: Vec<u32>,
 }
 
@@ -35,6 +65,10 @@ pub struct Scene {
 pub struct Node {
   #[serde(default, skip_serializing_if = "Option::is_none")]
   pub mesh: Option<u32>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub skin: Option<u32>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub camera: Option<u32>,
   #[serde(default, skip_serializing_if = "Vec::is_empty")]
   pub children: Vec<u32>,
   #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -43,20 +77,129 @@ pub struct Node {
   pub rotation: Option<[f32; 4]>,
   #[serde(default, skip_serializing_if = "Option::is_none")]
   pub scale: Option<[f32; 3]>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<NodeExtensions>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NodeExtensions {
+  #[serde(rename = "KHR_lights_punctual")]
+  pub lights_punctual: NodeLight,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NodeLight {
+  pub light: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Camera {
+  #[serde(rename = "type")]
+  pub camera_type: CameraType,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub perspective: Option<PerspectiveCamera>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub orthographic: Option<OrthographicCamera>,
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+pub enum CameraType {
+  #[serde(rename = "perspective")]
+  Perspective,
+  #[serde(rename = "orthographic")]
+  Orthographic,
+}
+
+/// A perspective camera, with the field of view in radians.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PerspectiveCamera {
+  #[serde(rename = "aspectRatio")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub aspect_ratio: Option<f32>,
+  pub yfov: f32,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub zfar: Option<f32>,
+  pub znear: f32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OrthographicCamera {
+  pub xmag: f32,
+  pub ymag: f32,
+  pub zfar: f32,
+  pub znear: f32,
+}
+
+/// A light of the `KHR_lights_punctual` extension, with the cone angles in radians.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Light {
+  #[serde(rename = "type")]
+  pub light_type: LightType,
+  pub color: [f32; 3],
+  pub intensity: f32,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub range: Option<f32>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub spot: Option<Spot>,
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+pub enum LightType {
+  #[serde(rename = "point")]
+  Point,
+  #[serde(rename = "spot")]
+  Spot,
+  #[serde(rename = "directional")]
+  Directional,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Spot {
+  #[serde(rename = "innerConeAngle")]
+  pub inner_cone_angle: f32,
+  #[serde(rename = "outerConeAngle")]
+  pub outer_cone_angle: f32,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Mesh {
   pub primitives: Vec<Primitive>,
+  /// The default weights of the morph targets of every primitive.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub weights: Vec<f32>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub extras: Option<MeshExtras>,
+}
+
+/// The names of the morph targets, which glTF has no field of its own for.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MeshExtras {
+  #[serde(rename = "targetNames")]
+  pub target_names: Vec<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Primitive {
   pub attributes: Attributes,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub indices: Option<u32>,
   #[serde(default)]
   #[serde(skip_serializing_if = "Option::is_none")]
   pub material: Option<u32>,
   pub mode: u32,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub targets: Vec<MorphTarget>,
+}
+
+/// The displacements of the vertex attributes of a primitive in a morph target.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MorphTarget {
+  #[serde(rename = "POSITION")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub position: Option<u32>,
+  #[serde(rename = "NORMAL")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub normal: Option<u32>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -64,13 +207,42 @@ pub struct Attributes {
   #[serde(rename = "POSITION")]
   #[serde(skip_serializing_if = "Option::is_none")]
   pub position: Option<u32>,
+  #[serde(rename = "NORMAL")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub normal: Option<u32>,
+  #[serde(rename = "TEXCOORD_0")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub texcoord_0: Option<u32>,
+  #[serde(rename = "COLOR_0")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub color_0: Option<u32>,
+  #[serde(rename = "JOINTS_0")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub joints_0: Option<u32>,
+  #[serde(rename = "WEIGHTS_0")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub weights_0: Option<u32>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Buffer {
-  pub uri: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub uri: Option<String>,
   #[serde(rename="byteLength")]
   pub byte_length: u32,
+  /// The contents of the buffer, written according to the chosen export format.
+  #[serde(skip)]
+  pub data: Vec<u8>,
+}
+
+impl Buffer {
+  pub fn new(data: Vec<u8>) -> Self {
+    Self {
+      uri: None,
+      byte_length: data.len() as u32,
+      data,
+    }
+  }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -96,7 +268,9 @@ pub struct Accessor {
   pub count: u32,
   #[serde(rename="type")]
   pub accessor_type: String,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
   pub max: Vec<f32>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
   pub min: Vec<f32>,
 }
 
@@ -105,18 +279,110 @@ pub struct Material {
   pub name: String,
   #[serde(rename = "pbrMetallicRoughness")]
   pub pbr_metallic_roughness: PBRMetallicRoughness,
+  #[serde(rename = "normalTexture")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub normal_texture: Option<TextureInfo>,
+  #[serde(rename = "occlusionTexture")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub occlusion_texture: Option<TextureInfo>,
+  #[serde(rename = "emissiveTexture")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub emissive_texture: Option<TextureInfo>,
+  #[serde(rename = "emissiveFactor")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub emissive_factor: Option<[f32; 3]>,
+  #[serde(rename = "alphaMode")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub alpha_mode: Option<AlphaMode>,
+  #[serde(rename = "alphaCutoff")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub alpha_cutoff: Option<f32>,
   #[serde(rename = "doubleSided")]
   pub double_sided: bool,
 }
 
+#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+pub enum AlphaMode {
+  #[serde(rename = "OPAQUE")]
+  Opaque,
+  #[serde(rename = "MASK")]
+  Mask,
+  #[serde(rename = "BLEND")]
+  Blend,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PBRMetallicRoughness {
   #[serde(rename = "baseColorFactor")]
   pub base_color_factor: [f32; 4],
+  #[serde(rename = "baseColorTexture")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub base_color_texture: Option<TextureInfo>,
   #[serde(rename = "metallicFactor")]
   pub metallic_factor: f32,
   #[serde(rename = "roughnessFactor")]
   pub roughness_factor: f32,
+  #[serde(rename = "metallicRoughnessTexture")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub metallic_roughness_texture: Option<TextureInfo>,
+}
+
+/// A reference from a material to a texture. `scale` is only used by normal textures and
+/// `strength` only by occlusion textures.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TextureInfo {
+  pub index: u32,
+  #[serde(rename = "texCoord")]
+  #[serde(default)]
+  pub tex_coord: u32,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub scale: Option<f32>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub strength: Option<f32>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Texture {
+  pub sampler: u32,
+  pub source: u32,
+}
+
+/// An image referenced by `uri`, or stored in a buffer view. When `data` is set, the exporter
+/// decides how the image is stored.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Image {
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub uri: Option<String>,
+  #[serde(rename = "mimeType")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub mime_type: Option<String>,
+  #[serde(rename = "bufferView")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub buffer_view: Option<u32>,
+  #[serde(skip)]
+  pub data: Option<Vec<u8>>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct TextureSampler {
+  #[serde(rename = "magFilter")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub mag_filter: Option<u32>,
+  #[serde(rename = "minFilter")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub min_filter: Option<u32>,
+  #[serde(rename = "wrapS")]
+  pub wrap_s: u32,
+  #[serde(rename = "wrapT")]
+  pub wrap_t: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Skin {
+  pub name: String,
+  #[serde(rename = "inverseBindMatrices")]
+  pub inverse_bind_matrices: u32,
+  pub joints: Vec<u32>,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
@@ -163,4 +429,6 @@ pub enum Path {
   Scale,
   #[serde(rename = "translation")]
   Translation,
+  #[serde(rename = "weights")]
+  Weights,
 }
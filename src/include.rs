@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::compile::apply_rotation_order;
+use crate::{parse_with_variables, DocumentPath, Error, Model, PathSegment};
+
+/// The lists of a model that included files add to, with the name of a single item.
+const LISTS: [(&str, &str); 7] = [
+  ("meshes", "mesh"),
+  ("nodes", "node"),
+  ("templates", "template"),
+  ("animations", "animation"),
+  ("materials", "material"),
+  ("skins", "skin"),
+  ("scenes", "scene"),
+];
+
+/// A model file with every file it includes merged into it. Each item of the merged lists
+/// remembers the file and index it came from, so that errors can point into that file.
+pub(crate) struct Loaded {
+  pub model: Model,
+  sources: Vec<Source>,
+  origins: Origins,
+}
+
+struct Source {
+  file: PathBuf,
+  src: String,
+}
+
+/// The file, as an index into the sources, and the index within its list that an item of a
+/// merged list came from.
+#[derive(Copy, Clone)]
+struct Origin {
+  source: usize,
+  index: usize,
+}
+
+type Origins = HashMap<&'static str, Vec<Origin>>;
+
+impl Loaded {
+  /// Read and parse a model file and, recursively, the files it includes. A file included more
+  /// than once is only merged the first time. The `variables` are set in the root file only.
+  /// The path of every file the model is made of is added to `files`, even when loading fails.
+  pub fn load(
+    file: &Path,
+    variables: &[(String, String)],
+    files: &mut Vec<PathBuf>,
+  ) -> Result<Self, Error> {
+    files.push(file.to_path_buf());
+    let mut loader = Loader { sources: Vec::new(), stack: Vec::new(), loaded: Vec::new(), files };
+    let canonical = file.canonicalize().map_err(|err| Error::from(err).in_file(file))?;
+    let (model, origins) = loader.load(file, canonical, variables)?;
+    Ok(Loaded { model, sources: loader.sources, origins })
+  }
+
+  /// Point an error in the merged model at the file and the position in it that it came from.
+  pub fn locate(&self, err: Error) -> Error {
+    let mut err = match err {
+      Error::Multiple(errors) => {
+        return Error::Multiple(errors.into_iter().map(|it| self.locate(it)).collect());
+      }
+      err => err,
+    };
+
+    let mut source = &self.sources[0];
+    if let Some(path) = err.path_mut() {
+      if let [PathSegment::Key(list), PathSegment::Index(index), ..] = path.0.as_mut_slice() {
+        if let Some(origin) = self.origins.get(list.as_str()).and_then(|it| it.get(*index)) {
+          source = &self.sources[origin.source];
+          *index = origin.index;
+        }
+      }
+    }
+    err.locate(&source.src).in_file(&source.file)
+  }
+}
+
+struct Loader<'a> {
+  sources: Vec<Source>,
+  /// The canonical paths of the files being loaded, from the root file down, with their sources.
+  stack: Vec<(PathBuf, usize)>,
+  /// The canonical paths of every file loaded so far.
+  loaded: Vec<PathBuf>,
+  /// The paths of the root file and every file included so far, whether they could be read or not.
+  files: &'a mut Vec<PathBuf>,
+}
+
+impl Loader<'_> {
+  fn load(
+    &mut self,
+    file: &Path,
+    canonical: PathBuf,
+    variables: &[(String, String)],
+  ) -> Result<(Model, Origins), Error> {
+    let src = std::fs::read_to_string(file).map_err(|err| Error::from(err).in_file(file))?;
+    let mut model = parse_with_variables(&src, variables).map_err(|err| err.in_file(file))?;
+
+    let source = self.sources.len();
+    let mut origins: Origins = LISTS.iter()
+      .map(|(list, _)| {
+        let origins = (0..names(&model, list).len()).map(|index| Origin { source, index });
+        (*list, origins.collect())
+      })
+      .collect();
+    self.sources.push(Source { file: file.to_path_buf(), src });
+    self.stack.push((canonical.clone(), source));
+    self.loaded.push(canonical);
+
+    // Errors in this file are located here, while errors in included files already are.
+    let mut errors = Vec::new();
+    let mut included_errors = Vec::new();
+    let dir = file.parent().unwrap_or_else(|| Path::new(""));
+    for (i, include) in std::mem::take(&mut model.include).iter().enumerate() {
+      let path = DocumentPath::root().key("include").index(i);
+      let included_file = dir.join(include);
+      if !self.files.contains(&included_file) {
+        self.files.push(included_file.clone());
+      }
+      let canonical = match included_file.canonicalize() {
+        Ok(canonical) => canonical,
+        Err(err) => {
+          errors.push(Error::invalid(path, format!("Cannot include '{}': {}", include, err)));
+          continue;
+        }
+      };
+      if let Some(start) = self.stack.iter().position(|(it, _)| *it == canonical) {
+        let cycle: Vec<String> = self.stack[start..].iter()
+          .map(|(_, source)| self.sources[*source].file.display().to_string())
+          .chain(std::iter::once(included_file.display().to_string()))
+          .collect();
+        errors.push(Error::invalid(path,
+          format!("Including '{}' forms a cycle: {}", include, cycle.join(" -> "))));
+        continue;
+      }
+      if self.loaded.contains(&canonical) {
+        continue;
+      }
+
+      let (mut included, included_origins) = match self.load(&included_file, canonical, &[]) {
+        Ok(loaded) => loaded,
+        Err(err) => {
+          included_errors.push(err);
+          continue;
+        }
+      };
+      settle(&mut included, Path::new(include).parent().unwrap_or_else(|| Path::new("")));
+
+      for (list, kind) in &LISTS {
+        let existing = names(&model, list);
+        for name in names(&included, list) {
+          if let Some(position) = existing.iter().position(|it| *it == name) {
+            let origin = origins[list][position];
+            let source = &self.sources[origin.source];
+            let location = DocumentPath::root().key(list).index(origin.index).key("name")
+              .locate(&source.src)
+              .map(|it| format!(":{}", it))
+              .unwrap_or_default();
+            errors.push(Error::invalid(path.clone(),
+              format!("Included {} '{}' is already defined at {}{}", kind, name,
+                source.file.display(), location)));
+          }
+        }
+        origins.get_mut(list).unwrap().extend(&included_origins[list]);
+      }
+      append(&mut model, included);
+    }
+    self.stack.pop();
+
+    let src = &self.sources[source].src;
+    errors = errors.into_iter().map(|it| it.locate(src).in_file(file)).collect();
+    errors.extend(included_errors);
+    if errors.is_empty() {
+      Ok((model, origins))
+    } else {
+      Err(Error::from_errors(errors))
+    }
+  }
+}
+
+/// Make an included model independent of the file that includes it: its own `flat` and
+/// `rotationOrder` settings are given to every primitive and rotation that does not set its own,
+/// and image paths are made relative to the including file, with `dir` the directory of the
+/// included file relative to it.
+fn settle(model: &mut Model, dir: &Path) {
+  apply_rotation_order(model);
+  let (rotation_order, flat) = (model.rotation_order, model.flat);
+  for channel in model.animations.iter_mut().flat_map(|it| &mut it.channels) {
+    channel.order.get_or_insert(rotation_order);
+  }
+  for primitive in model.meshes.iter_mut().flat_map(|it| &mut it.primitives) {
+    primitive.flat.get_or_insert(flat);
+  }
+
+  for material in &mut model.materials {
+    let mut textures = [
+      &mut material.base_color_texture,
+      &mut material.metallic_roughness_texture,
+      &mut material.normal_texture,
+      &mut material.occlusion_texture,
+      &mut material.emissive_texture,
+    ];
+    for texture in textures.iter_mut().filter_map(|it| it.as_mut()) {
+      if !texture.image.starts_with("data:") && Path::new(&texture.image).is_relative() {
+        texture.image = dir.join(&texture.image).to_string_lossy().into_owned();
+      }
+    }
+  }
+}
+
+/// The names of the items in one of the `LISTS` of a model.
+fn names<'a>(model: &'a Model, list: &str) -> Vec<&'a str> {
+  match list {
+    "meshes" => model.meshes.iter().map(|it| it.name.as_str()).collect(),
+    "nodes" => model.nodes.iter().map(|it| it.name.as_str()).collect(),
+    "templates" => model.templates.iter().map(|it| it.name.as_str()).collect(),
+    "animations" => model.animations.iter().map(|it| it.name.as_str()).collect(),
+    "materials" => model.materials.iter().map(|it| it.name.as_str()).collect(),
+    "skins" => model.skins.iter().map(|it| it.name.as_str()).collect(),
+    "scenes" => model.scenes.iter().map(|it| it.name.as_str()).collect(),
+    _ => Vec::new(),
+  }
+}
+
+/// Add the items of every one of the `LISTS` of `included` to the end of the lists of `model`.
+fn append(model: &mut Model, included: Model) {
+  model.meshes.extend(included.meshes);
+  model.nodes.extend(included.nodes);
+  model.templates.extend(included.templates);
+  model.animations.extend(included.animations);
+  model.materials.extend(included.materials);
+  model.skins.extend(included.skins);
+  model.scenes.extend(included.scenes);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Write the files of a test into a directory of their own and load the first one.
+  fn load(test: &str, files: &[(&str, &str)]) -> (Result<Loaded, Error>, Vec<PathBuf>) {
+    let dir = std::env::temp_dir()
+      .join(format!("declarative-models-{}-{}", test, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for (name, src) in files {
+      std::fs::write(dir.join(name), src).unwrap();
+    }
+    let mut sources = Vec::new();
+    let loaded = Loaded::load(&dir.join(files[0].0), &[], &mut sources);
+    std::fs::remove_dir_all(&dir).unwrap();
+    (loaded, sources.iter().map(|it| it.strip_prefix(&dir).unwrap().to_path_buf()).collect())
+  }
+
+  fn message(result: Result<Loaded, Error>) -> String {
+    match result {
+      Ok(_) => panic!("loading succeeded"),
+      Err(err) => err.to_string(),
+    }
+  }
+
+  #[test]
+  fn included_items_are_appended() {
+    let (loaded, sources) = load("append", &[
+      ("model.yml", "include: [ a.yml, b.yml ]\nmaterials: [{ name: red }]"),
+      ("a.yml", "include: [ b.yml ]\nmaterials: [{ name: green }]"),
+      ("b.yml", "materials: [{ name: blue }]"),
+    ]);
+    let model = loaded.unwrap().model;
+    let names: Vec<&str> = model.materials.iter().map(|it| it.name.as_str()).collect();
+    assert_eq!(names, ["red", "green", "blue"]);
+    assert_eq!(sources, [Path::new("model.yml"), Path::new("a.yml"), Path::new("b.yml")]);
+  }
+
+  #[test]
+  fn include_cycles_are_errors() {
+    let (loaded, sources) = load("cycle", &[
+      ("model.yml", "include: [ a.yml ]"),
+      ("a.yml", "include: [ b.yml ]"),
+      ("b.yml", "include: [ a.yml ]"),
+    ]);
+    let message = message(loaded);
+    assert!(message.contains("b.yml:1:"), "{}", message);
+    let (_, cycle) = message.split_once("Including 'a.yml' forms a cycle: ").unwrap();
+    let cycle: Vec<&str> = cycle.split(" -> ").map(|it| &it[it.len() - 5..]).collect();
+    assert_eq!(cycle, ["a.yml", "b.yml", "a.yml"]);
+    assert_eq!(sources.len(), 3);
+  }
+
+  #[test]
+  fn items_defined_twice_are_errors() {
+    let (loaded, _) = load("conflict", &[
+      ("model.yml", "include: [ a.yml ]\nmaterials: [{ name: red }, { name: red2 }]"),
+      ("a.yml", "materials: [{ name: blue }, { name: red2 }]"),
+    ]);
+    let message = message(loaded);
+    assert!(message.contains("Included material 'red2' is already defined at "), "{}", message);
+    assert!(message.contains("model.yml:2:"), "{}", message);
+  }
+
+  #[test]
+  fn missing_includes_are_still_sources() {
+    let (loaded, sources) = load("missing", &[("model.yml", "include: [ missing.yml ]")]);
+    assert!(message(loaded).contains("Cannot include 'missing.yml'"));
+    assert_eq!(sources, [Path::new("model.yml"), Path::new("missing.yml")]);
+  }
+}
@@ -0,0 +1,81 @@
+//! Generate animated glTF meshes from declarative YAML models.
+//!
+//! ```no_run
+//! let model = declarative_models::parse(&std::fs::read_to_string("bear.yml")?)?;
+//! let gltf = declarative_models::compile(&model)?;
+//! std::fs::write("bear.glb", declarative_models::export::to_glb(&gltf)?)?;
+//! # Ok::<(), declarative_models::Error>(())
+//! ```
+
+use std::path::{Path, PathBuf};
+
+pub use color::Color;
+pub use compile::compile;
+pub use error::{Error, ReferenceKind};
+pub use geometry::*;
+pub use geometry_buffer::GeometryBuffer;
+pub use location::{DocumentPath, Location, PathSegment};
+pub use model::*;
+pub use validate::validate;
+
+mod color;
+mod compile;
+mod error;
+pub mod export;
+mod expression;
+mod geometry;
+pub mod geometry_buffer;
+pub mod gltf;
+mod include;
+mod location;
+mod model;
+mod validate;
+
+/// Parse a model from YAML source, evaluating its variables and expressions.
+pub fn parse(src: &str) -> Result<Model, Error> {
+  parse_with_variables(src, &[])
+}
+
+/// Parse a model from YAML source like `parse`, with some of the variables it defines set to the
+/// value of another expression, given as pairs of name and expression.
+pub fn parse_with_variables(src: &str, variables: &[(String, String)]) -> Result<Model, Error> {
+  let substituted = expression::substitute(src, variables)?;
+  serde_yaml::from_str(&substituted.src).map_err(|err| match Error::from(err) {
+    Error::Parse { file, location, source } => Error::Parse {
+      file,
+      location: location.map(|it| substituted.original_location(it)),
+      source,
+    },
+    err => err,
+  })
+}
+
+/// Read, parse and compile a model file along with the files it includes, loading the images it
+/// uses. Errors name the file and, where possible, the line and column they were raised from.
+pub fn compile_file(path: impl AsRef<Path>) -> Result<gltf::Gltf, Error> {
+  compile_file_with_variables(path, &[])
+}
+
+/// Compile a model file like `compile_file`, with some of the variables it defines set like
+/// `parse_with_variables` does. The variables of the files it includes are left as they are.
+pub fn compile_file_with_variables(
+  path: impl AsRef<Path>,
+  variables: &[(String, String)],
+) -> Result<gltf::Gltf, Error> {
+  compile_file_with_sources(path, variables, &mut Vec::new())
+}
+
+/// Compile a model file like `compile_file_with_variables`, adding the paths of the file and of
+/// every file it includes to `sources`, even when compiling fails. A model has to be compiled
+/// again when any of them changes.
+pub fn compile_file_with_sources(
+  path: impl AsRef<Path>,
+  variables: &[(String, String)],
+  sources: &mut Vec<PathBuf>,
+) -> Result<gltf::Gltf, Error> {
+  let path = path.as_ref();
+  let loaded = include::Loaded::load(path, variables, sources)?;
+  let mut gltf = compile(&loaded.model).map_err(|err| loaded.locate(err))?;
+  export::load_images(&mut gltf, path.parent().unwrap_or_else(|| Path::new("")))?;
+  Ok(gltf)
+}
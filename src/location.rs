@@ -0,0 +1,153 @@
+use std::fmt;
+
+use yaml_rust::parser::{Event, Parser};
+use yaml_rust::scanner::Marker;
+
+/// A position in a YAML source file. Lines and columns are counted from 1.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Location {
+  pub line: usize,
+  pub column: usize,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PathSegment {
+  Key(String),
+  Index(usize),
+}
+
+/// The path to a value inside a document, e.g. `meshes[2].primitives[0].material`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DocumentPath(pub Vec<PathSegment>);
+
+impl DocumentPath {
+  pub fn root() -> Self {
+    Self::default()
+  }
+
+  pub fn index(&self, index: usize) -> Self {
+    let mut path = self.clone();
+    path.0.push(PathSegment::Index(index));
+    path
+  }
+
+  pub fn is_root(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  pub fn key(&self, key: &str) -> Self {
+    let mut path = self.clone();
+    path.0.push(PathSegment::Key(key.to_string()));
+    path
+  }
+
+  /// Find the location of the value at this path in the YAML source. If the value does not
+  /// exist, the location of its closest existing parent is returned instead.
+  pub fn locate(&self, src: &str) -> Option<Location> {
+    let mut found = None;
+    let mut closest = None;
+    walk_values(src, |path, _, marker| {
+      let location = Location { line: marker.line(), column: marker.col() + 1 };
+      if path == *self {
+        found = Some(location);
+        return false;
+      }
+      if self.0.starts_with(&path.0) {
+        closest = Some(location);
+      }
+      true
+    });
+    found.or(closest)
+  }
+}
+
+/// Visit every value of a YAML document in order with its path, its event and where it starts.
+/// Values are scalars, aliases, and the starts of sequences and mappings, but not mapping keys.
+/// The walk stops when `visit` returns false. Returns `None` if the source is not valid YAML or
+/// has a mapping key that is not a scalar, which cannot be expressed as a document path.
+pub(crate) fn walk_values(
+  src: &str,
+  mut visit: impl FnMut(DocumentPath, &Event, Marker) -> bool,
+) -> Option<()> {
+  enum Frame {
+    Mapping { path: DocumentPath, key: Option<String> },
+    Sequence { path: DocumentPath, index: usize },
+  }
+
+  let mut parser = Parser::new(src.chars());
+  let mut stack: Vec<Frame> = Vec::new();
+
+  loop {
+    let (event, marker) = parser.next().ok()?;
+    match event {
+      Event::Scalar(..) | Event::Alias(_) | Event::SequenceStart(_) | Event::MappingStart(_) => {
+        let path = match stack.last_mut() {
+          None => DocumentPath::root(),
+          Some(Frame::Mapping { key, .. }) if key.is_none() => {
+            match event {
+              Event::Scalar(value, ..) => {
+                *key = Some(value);
+                continue;
+              }
+              _ => return None,
+            }
+          }
+          Some(Frame::Mapping { path, key }) => path.key(&key.take().unwrap_or_default()),
+          Some(Frame::Sequence { path, index }) => {
+            *index += 1;
+            path.index(*index - 1)
+          }
+        };
+
+        let frame = match event {
+          Event::SequenceStart(_) => Some(Frame::Sequence { path: path.clone(), index: 0 }),
+          Event::MappingStart(_) => Some(Frame::Mapping { path: path.clone(), key: None }),
+          _ => None,
+        };
+        if !visit(path, &event, marker) {
+          return Some(());
+        }
+        stack.extend(frame);
+      }
+      Event::SequenceEnd | Event::MappingEnd => {
+        stack.pop();
+      }
+      Event::StreamEnd => return Some(()),
+      _ => {}
+    }
+  }
+}
+
+impl fmt::Display for DocumentPath {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (i, segment) in self.0.iter().enumerate() {
+      match segment {
+        PathSegment::Key(key) if i == 0 => write!(f, "{}", key)?,
+        PathSegment::Key(key) => write!(f, ".{}", key)?,
+        PathSegment::Index(index) => write!(f, "[{}]", index)?,
+      }
+    }
+    Ok(())
+  }
+}
+
+impl fmt::Display for Location {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}:{}", self.line, self.column)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn missing_values_are_located_at_their_closest_parent() {
+    let src = "meshes:\n  - name: a\n    primitives: [{ material: red }]\n";
+    let path = DocumentPath::root().key("meshes").index(0).key("primitives").index(0);
+    assert_eq!(path.key("material").locate(src), Some(Location { line: 3, column: 30 }));
+    assert_eq!(path.key("geometry").locate(src), Some(Location { line: 3, column: 18 }));
+    let name = DocumentPath::root().key("meshes").index(0).key("name");
+    assert_eq!(name.locate(src), Some(Location { line: 2, column: 11 }));
+  }
+}
@@ -1,23 +1,110 @@
+use std::collections::BTreeMap;
 use std::ops::{Add, Div, Mul, Sub};
 
 use serde::{Deserialize, Serialize};
 
-use crate::Geometry;
+use crate::{Color, Geometry};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Model {
+  /// Other model files, relative to this one, whose meshes, nodes, templates, animations,
+  /// materials, skins and scenes are added to this model. Names must not be defined twice across
+  /// the files. Each included file keeps its own `flat` and `rotationOrder`, and its other settings
+  /// are ignored.
+  #[serde(default)]
+  pub include: Vec<String>,
+  /// Numbers and `{ x, y, z }` vectors that expressions in this file can use inside `${ }`, as in
+  /// `${radius}` or `${2 * radius}`. A variable can be an expression itself, using other variables.
+  #[serde(default)]
+  pub variables: BTreeMap<String, Variable>,
+  #[serde(default)]
   pub meshes: Vec<Mesh>,
+  #[serde(default)]
   pub nodes: Vec<Node>,
+  /// Nodes with their children that other nodes can instance by name with `Node::template`.
+  #[serde(default)]
+  pub templates: Vec<Node>,
   #[serde(default)]
   pub animations: Vec<Animation>,
   #[serde(default)]
   pub materials: Vec<Material>,
+  #[serde(default)]
+  pub skins: Vec<Skin>,
+  /// Named sets of root nodes. When left out, the document has a single scene with every root
+  /// node.
+  #[serde(default)]
+  pub scenes: Vec<Scene>,
+  /// The scene shown when the document is opened, which defaults to the first scene.
+  #[serde(rename = "defaultScene")]
+  #[serde(default)]
+  pub default_scene: Option<String>,
+  /// Write every primitive as non-indexed triangles with flat normals.
+  #[serde(default)]
+  pub flat: bool,
+  /// The order of Euler angle rotations that do not set their own.
+  #[serde(rename = "rotationOrder")]
+  #[serde(default)]
+  pub rotation_order: RotationOrder,
+}
+
+/// The value of a variable, after its expressions are evaluated.
+#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Variable {
+  Number(f32),
+  Vector(Vector3),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Scene {
+  pub name: String,
+  /// The names of the root nodes in the scene.
+  pub nodes: Vec<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Mesh {
   pub name: String,
   pub primitives: Vec<Primitive>,
+  /// Morph targets that the mesh can blend towards, animated by `Target::Weights` channels.
+  #[serde(default)]
+  pub targets: Vec<MorphTarget>,
+}
+
+/// A variant of a mesh with the same vertices in different positions. The geometry of every
+/// primitive is replaced by `primitives` when it is given, and then scaled, offset and deformed.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MorphTarget {
+  pub name: String,
+  /// The weight of the target when it is not animated.
+  #[serde(default)]
+  pub weight: f32,
+  #[serde(default)]
+  pub primitives: Option<Vec<MorphPrimitive>>,
+  #[serde(default)]
+  pub scale: Option<Vector3>,
+  #[serde(default)]
+  pub offset: Option<Vector3>,
+  #[serde(default)]
+  pub deform: Option<MorphDeform>,
+}
+
+/// The geometry of a primitive in a morph target, which must generate the same number of vertices
+/// and triangles as the primitive itself.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MorphPrimitive {
+  pub geometry: Vec<Geometry>,
+}
+
+/// Moves every vertex of a morph target by a random offset, like `Deform`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MorphDeform {
+  #[serde(default)]
+  pub seed: u64,
+  #[serde(default = "Vector3::minus_one")]
+  pub min: Vector3,
+  #[serde(default = "Vector3::one")]
+  pub max: Vector3,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -25,6 +112,12 @@ pub struct Primitive {
   #[serde(default)]
   pub material: Option<String>,
   pub geometry: Vec<Geometry>,
+  /// Overrides `Model::flat` for this primitive.
+  #[serde(default)]
+  pub flat: Option<bool>,
+  /// The joint that every vertex of the primitive follows when the mesh is skinned.
+  #[serde(default)]
+  pub joint: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -35,13 +128,174 @@ pub struct Node {
   #[serde(default)]
   pub offset: Option<Vector3>,
   #[serde(default)]
-  pub rotation: Option<Vector3>,
+  pub rotation: Option<Rotation>,
   #[serde(default)]
   pub scale: Option<Vector3>,
+  /// The skin that deforms the mesh of the node. The transform of a skinned node is ignored, and
+  /// its mesh is placed relative to the root of the model.
+  #[serde(default)]
+  pub skin: Option<String>,
+  /// A camera looking down the negative z axis of the node, with y up.
+  #[serde(default)]
+  pub camera: Option<Camera>,
+  /// A light exported with the `KHR_lights_punctual` extension. Spot and directional lights shine
+  /// down the negative z axis of the node.
+  #[serde(default)]
+  pub light: Option<Light>,
+  /// A template this node is an instance of. The node takes every field it does not set from the
+  /// template, and copies of the children of the template are added to its children, named
+  /// `<node>/<child>`.
+  #[serde(default)]
+  pub template: Option<String>,
+  /// Replaces the material of every primitive of the mesh of the node, and of the nodes copied
+  /// from its template.
+  #[serde(default)]
+  pub material: Option<String>,
+  /// Repeats the node with its children, applying the transform of each copy after the transform
+  /// of the node. `{i}` in the name of the node is replaced by the index of the copy, which is
+  /// appended to the name when it has no `{i}`. The children of each copy are named the same way,
+  /// or `<copy>/<child>` when their name has no `{i}`. Templates cannot have an array.
+  ///
+  /// Copies keep an offset, rotation and scale like any node, so the transform is only
+  /// approximate when the array scales unevenly and the node has a rotation of its own: the scale
+  /// is then applied along the axes of the node instead of the axes of the array.
+  #[serde(default)]
+  pub array: Option<ArrayPattern>,
   #[serde(default)]
   pub children: Vec<Node>,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum Camera {
+  Perspective(PerspectiveCamera),
+  Orthographic(OrthographicCamera),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PerspectiveCamera {
+  /// The vertical field of view in degrees.
+  #[serde(default = "default_yfov")]
+  pub yfov: f32,
+  /// The width of the view divided by its height, or the aspect ratio of the viewport when left
+  /// out.
+  #[serde(rename = "aspectRatio")]
+  #[serde(default)]
+  pub aspect_ratio: Option<f32>,
+  #[serde(default = "default_znear")]
+  pub znear: f32,
+  /// The far clipping distance, or no far clipping plane when left out.
+  #[serde(default)]
+  pub zfar: Option<f32>,
+}
+
+fn default_yfov() -> f32 {
+  60.0
+}
+
+fn default_znear() -> f32 {
+  0.1
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OrthographicCamera {
+  /// Half the width of the view.
+  pub xmag: f32,
+  /// Half the height of the view.
+  pub ymag: f32,
+  #[serde(default = "default_znear")]
+  pub znear: f32,
+  pub zfar: f32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum Light {
+  /// Shines in every direction from the node.
+  Point(PointLight),
+  /// Shines in a cone from the node.
+  Spot(SpotLight),
+  /// Shines in parallel rays, like sunlight, from infinitely far away.
+  Directional(DirectionalLight),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PointLight {
+  #[serde(default)]
+  pub color: Color,
+  /// The luminous intensity in candela.
+  #[serde(default = "default_intensity")]
+  pub intensity: f32,
+  /// The distance at which the light reaches zero, or no limit when left out.
+  #[serde(default)]
+  pub range: Option<f32>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SpotLight {
+  #[serde(default)]
+  pub color: Color,
+  /// The luminous intensity in candela.
+  #[serde(default = "default_intensity")]
+  pub intensity: f32,
+  /// The distance at which the light reaches zero, or no limit when left out.
+  #[serde(default)]
+  pub range: Option<f32>,
+  /// The angle in degrees from the center of the cone at which the light starts to fall off.
+  #[serde(rename = "innerConeAngle")]
+  #[serde(default)]
+  pub inner_cone_angle: f32,
+  /// The angle in degrees from the center of the cone at which the light reaches zero.
+  #[serde(rename = "outerConeAngle")]
+  #[serde(default = "default_outer_cone_angle")]
+  pub outer_cone_angle: f32,
+}
+
+fn default_outer_cone_angle() -> f32 {
+  45.0
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DirectionalLight {
+  #[serde(default)]
+  pub color: Color,
+  /// The illuminance in lux.
+  #[serde(default = "default_intensity")]
+  pub intensity: f32,
+}
+
+fn default_intensity() -> f32 {
+  1.0
+}
+
+/// A set of joint nodes that deform the meshes they skin.
+///
+/// Every vertex follows the `joint` of its primitive if it has one, otherwise the joints of the
+/// volumes containing it, weighted equally between up to four of them, and otherwise the nearest
+/// joint. Vertices and volumes are placed relative to the root of the model, in the
+/// pose the nodes are written in.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Skin {
+  pub name: String,
+  pub joints: Vec<String>,
+  #[serde(default)]
+  pub volumes: Vec<JointVolume>,
+}
+
+/// A box of vertices that follow a joint.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct JointVolume {
+  pub joint: String,
+  pub min: Vector3,
+  pub max: Vector3,
+}
+
+impl JointVolume {
+  pub fn contains(&self, point: Vector3) -> bool {
+    (self.min.x..=self.max.x).contains(&point.x)
+      && (self.min.y..=self.max.y).contains(&point.y)
+      && (self.min.z..=self.max.z).contains(&point.z)
+  }
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct Animation {
   pub name: String,
@@ -52,7 +306,82 @@ pub struct Animation {
 pub struct Channel {
   pub nodes: Vec<String>,
   pub target: Target,
-  pub keyframes: Vec<(f32, Vector3)>,
+  pub keyframes: Vec<(f32, KeyframeValue)>,
+  #[serde(default)]
+  pub interpolation: Interpolation,
+  /// The in-tangent and out-tangent of every keyframe of a `CubicSpline` translation or scale
+  /// channel, in units per second. Tangents are calculated from the neighbouring keyframes when
+  /// left out.
+  #[serde(default)]
+  pub tangents: Option<Vec<[Vector3; 2]>>,
+  /// The order of Euler angle rotation keyframes, overriding `Model::rotation_order`.
+  #[serde(default)]
+  pub order: Option<RotationOrder>,
+}
+
+/// The value of a keyframe. Rotations are written as Euler angles in degrees, as a quaternion, or
+/// as an angle in degrees around an axis, which can go past 360 for continuous spins. Morph target
+/// weights are written as `{ weights: [...] }`, with one weight per target of the mesh.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum KeyframeValue {
+  Weights {
+    weights: Vec<f32>,
+  },
+  Quaternion {
+    #[serde(default)]
+    x: f32,
+    #[serde(default)]
+    y: f32,
+    #[serde(default)]
+    z: f32,
+    w: f32,
+  },
+  AxisAngle {
+    axis: Vector3,
+    angle: f32,
+  },
+  Vector(Vector3),
+}
+
+impl KeyframeValue {
+  pub fn vector(&self) -> Option<Vector3> {
+    match self {
+      KeyframeValue::Vector(vector) => Some(*vector),
+      KeyframeValue::Weights { .. }
+      | KeyframeValue::Quaternion { .. }
+      | KeyframeValue::AxisAngle { .. } => None,
+    }
+  }
+
+  pub fn weights(&self) -> Option<&[f32]> {
+    match self {
+      KeyframeValue::Weights { weights } => Some(weights),
+      KeyframeValue::Quaternion { .. }
+      | KeyframeValue::AxisAngle { .. }
+      | KeyframeValue::Vector(_) => None,
+    }
+  }
+
+  /// Convert a rotation keyframe to a quaternion, reading vectors as Euler angles in `order`.
+  pub fn to_quat(&self, order: RotationOrder) -> glam::Quat {
+    match *self {
+      KeyframeValue::Quaternion { x, y, z, w } => Rotation::Quaternion { x, y, z, w }.to_quat(),
+      KeyframeValue::AxisAngle { axis, angle } => Rotation::AxisAngle { axis, angle }.to_quat(),
+      KeyframeValue::Vector(angles) => order.to_quat(angles),
+      KeyframeValue::Weights { .. } => glam::Quat::IDENTITY,
+    }
+  }
+}
+
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Interpolation {
+  #[default]
+  Linear,
+  /// Every keyframe holds its value until the next keyframe.
+  Step,
+  /// A smooth curve through the keyframes, shaped by their tangents.
+  CubicSpline,
 }
 
 #[derive(Copy, Clone, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
@@ -62,6 +391,173 @@ pub enum Axis {
   Z,
 }
 
+/// A rotation of a node or a geometry entry. Euler angles are in degrees and use the `order` given
+/// with them, or `Model::rotation_order` otherwise.
+#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Rotation {
+  Quaternion {
+    #[serde(default)]
+    x: f32,
+    #[serde(default)]
+    y: f32,
+    #[serde(default)]
+    z: f32,
+    w: f32,
+  },
+  AxisAngle {
+    axis: Vector3,
+    angle: f32,
+  },
+  Euler {
+    #[serde(default)]
+    x: f32,
+    #[serde(default)]
+    y: f32,
+    #[serde(default)]
+    z: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    order: Option<RotationOrder>,
+  },
+}
+
+impl Rotation {
+  pub fn to_quat(&self) -> glam::Quat {
+    match *self {
+      Rotation::Quaternion { x, y, z, w } => glam::Quat::from_xyzw(x, y, z, w).normalize(),
+      Rotation::AxisAngle { axis, angle } => {
+        let axis = axis.normalize();
+        glam::Quat::from_axis_angle(glam::vec3(axis.x, axis.y, axis.z), angle.to_radians())
+      }
+      Rotation::Euler { x, y, z, order } => order.unwrap_or_default().to_quat(Vector3::new(x, y, z)),
+    }
+  }
+}
+
+/// The order Euler angles are applied in, around the fixed axes of the parent. `Xyz` rotates
+/// around the x axis first and the z axis last.
+///
+/// The default, `Legacy`, keeps the rotations of models written before the order could be chosen:
+/// the `z` angle turns around the x axis first, then `y` around the y axis, then `x` around the z
+/// axis.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum RotationOrder {
+  #[default]
+  Legacy,
+  Xyz,
+  Xzy,
+  Yxz,
+  Yzx,
+  Zxy,
+  Zyx,
+}
+
+impl RotationOrder {
+  /// Convert Euler angles in degrees to a quaternion.
+  pub fn to_quat(&self, angles: Vector3) -> glam::Quat {
+    let x = glam::Quat::from_rotation_x(angles.x.to_radians());
+    let y = glam::Quat::from_rotation_y(angles.y.to_radians());
+    let z = glam::Quat::from_rotation_z(angles.z.to_radians());
+
+    match self {
+      RotationOrder::Legacy => {
+        RotationOrder::Xyz.to_quat(Vector3::new(angles.z, angles.y, angles.x))
+      }
+      RotationOrder::Xyz => z * y * x,
+      RotationOrder::Xzy => y * z * x,
+      RotationOrder::Yxz => z * x * y,
+      RotationOrder::Yzx => x * z * y,
+      RotationOrder::Zxy => y * x * z,
+      RotationOrder::Zyx => x * y * z,
+    }.normalize()
+  }
+}
+
+/// How a node or a geometry entry is repeated. Copy `i`, counting from 0, is scaled, rotated around
+/// the origin and then moved by the pattern.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum ArrayPattern {
+  /// Copies in a row, each moved, rotated and scaled by the steps from the one before.
+  Linear {
+    count: u32,
+    #[serde(default)]
+    offset: Vector3,
+    #[serde(default)]
+    rotation: Option<Rotation>,
+    #[serde(default = "Vector3::one")]
+    scale: Vector3,
+  },
+  /// Copies `spacing` apart in a grid of `count` copies along each axis, numbered along x first.
+  Grid {
+    count: GridCount,
+    spacing: Vector3,
+  },
+  /// Copies around an axis, each turned by `angle` degrees from the one before. The angle defaults
+  /// to a full turn divided by the count.
+  Radial {
+    count: u32,
+    axis: Axis,
+    #[serde(default)]
+    angle: Option<f32>,
+  },
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+pub struct GridCount {
+  #[serde(default = "default_grid_count")]
+  pub x: u32,
+  #[serde(default = "default_grid_count")]
+  pub y: u32,
+  #[serde(default = "default_grid_count")]
+  pub z: u32,
+}
+
+fn default_grid_count() -> u32 {
+  1
+}
+
+impl ArrayPattern {
+  /// The total number of copies.
+  pub fn count(&self) -> u32 {
+    match self {
+      ArrayPattern::Linear { count, .. } | ArrayPattern::Radial { count, .. } => *count,
+      ArrayPattern::Grid { count, .. } => count.x * count.y * count.z,
+    }
+  }
+
+  /// The scale, rotation and offset of copy `i`, in the order they are applied.
+  pub fn transform(&self, i: u32) -> (Vector3, glam::Quat, Vector3) {
+    let steps = i as f32;
+    match self {
+      ArrayPattern::Linear { offset, rotation, scale, .. } => {
+        let rotation = rotation.map(|it| {
+          let (axis, angle) = it.to_quat().to_axis_angle();
+          glam::Quat::from_axis_angle(axis, angle * steps)
+        });
+        let scale = Vector3::new(scale.x.powi(i as i32), scale.y.powi(i as i32),
+          scale.z.powi(i as i32));
+        let offset = *offset * Vector3::new(steps, steps, steps);
+        (scale, rotation.unwrap_or(glam::Quat::IDENTITY), offset)
+      }
+      ArrayPattern::Grid { count, spacing } => {
+        let index = Vector3::new((i % count.x) as f32, (i / count.x % count.y) as f32,
+          (i / (count.x * count.y)) as f32);
+        (Vector3::ONE, glam::Quat::IDENTITY, index * *spacing)
+      }
+      ArrayPattern::Radial { count, axis, angle } => {
+        let angle = (angle.unwrap_or(360.0 / *count as f32) * steps).to_radians();
+        let rotation = match axis {
+          Axis::X => glam::Quat::from_rotation_x(angle),
+          Axis::Y => glam::Quat::from_rotation_y(angle),
+          Axis::Z => glam::Quat::from_rotation_z(angle),
+        };
+        (Vector3::ONE, rotation, Vector3::ZERO)
+      }
+    }
+  }
+}
+
 #[derive(Copy, Clone, Debug, Default, Deserialize, Serialize)]
 #[repr(C)]
 pub struct Vector3 {
@@ -82,10 +578,18 @@ impl Vector3 {
   pub const ONE: Vector3 = Vector3 { x: 1.0, y: 1.0, z: 1.0 };
   pub const MINUS_ONE: Vector3 = Vector3 { x: -1.0, y: -1.0, z: -1.0 };
 
+  pub fn cross(&self, other: Vector3) -> Self {
+    glam::vec3(self.x, self.y, self.z).cross(glam::vec3(other.x, other.y, other.z)).into()
+  }
+
   pub fn distance(&self, other: Vector3) -> f32 {
     glam::vec3(self.x, self.y, self.z).distance(glam::vec3(other.x, other.y, other.z))
   }
 
+  pub fn dot(&self, other: Vector3) -> f32 {
+    self.x * other.x + self.y * other.y + self.z * other.z
+  }
+
   pub fn get_axis(&self, axis: Axis) -> f32 {
     match axis {
       Axis::X => self.x,
@@ -118,6 +622,11 @@ impl Vector3 {
     Self { x, y, z }
   }
 
+  /// Normalize the vector, leaving zero-length vectors as zero.
+  pub fn normalize(&self) -> Self {
+    glam::vec3(self.x, self.y, self.z).normalize_or_zero().into()
+  }
+
   pub fn one() -> Self {
     Vector3::ONE
   }
@@ -185,6 +694,12 @@ impl Sub for Vector3 {
   }
 }
 
+impl From<glam::Vec3> for Vector3 {
+  fn from(xyz: glam::Vec3) -> Self {
+    Self::new(xyz.x, xyz.y, xyz.z)
+  }
+}
+
 impl From<Vector3> for [f32; 3] {
   fn from(xyz: Vector3) -> Self {
     [xyz.x, xyz.y, xyz.z]
@@ -201,11 +716,107 @@ impl From<Vector3> for Vec<f32> {
 pub struct Material {
   pub name: String,
   #[serde(rename = "baseColor")]
-  pub base_color: [f32; 4],
+  #[serde(default)]
+  pub base_color: Color,
   #[serde(default)]
   pub metallic: f32,
   #[serde(default)]
   pub roughness: f32,
+  #[serde(rename = "baseColorTexture")]
+  #[serde(default)]
+  pub base_color_texture: Option<Texture>,
+  /// Metalness is read from the blue channel and roughness from the green channel.
+  #[serde(rename = "metallicRoughnessTexture")]
+  #[serde(default)]
+  pub metallic_roughness_texture: Option<Texture>,
+  #[serde(rename = "normalTexture")]
+  #[serde(default)]
+  pub normal_texture: Option<Texture>,
+  #[serde(rename = "occlusionTexture")]
+  #[serde(default)]
+  pub occlusion_texture: Option<Texture>,
+  #[serde(rename = "emissiveTexture")]
+  #[serde(default)]
+  pub emissive_texture: Option<Texture>,
+  /// The color of light emitted by the material, multiplied by the emissive texture if there is
+  /// one.
+  #[serde(default)]
+  pub emissive: Option<Color>,
+  #[serde(rename = "alphaMode")]
+  #[serde(default)]
+  pub alpha_mode: AlphaMode,
+  /// The alpha below which a `Mask` material is fully transparent. Defaults to 0.5.
+  #[serde(rename = "alphaCutoff")]
+  #[serde(default)]
+  pub alpha_cutoff: Option<f32>,
+  /// Render the back faces of triangles as well as the front faces.
+  #[serde(rename = "doubleSided")]
+  #[serde(default = "default_double_sided")]
+  pub double_sided: bool,
+}
+
+fn default_double_sided() -> bool {
+  true
+}
+
+/// How the alpha of the base color is used.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum AlphaMode {
+  /// Alpha is ignored and the material is fully opaque.
+  #[default]
+  Opaque,
+  /// The material is either fully opaque or fully transparent, depending on `alphaCutoff`.
+  Mask,
+  /// The material is blended with what is behind it.
+  Blend,
+}
+
+/// An image file used by a material, with the path relative to the model file.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Texture {
+  pub image: String,
+  #[serde(rename = "wrapS")]
+  #[serde(default)]
+  pub wrap_s: Wrap,
+  #[serde(rename = "wrapT")]
+  #[serde(default)]
+  pub wrap_t: Wrap,
+  #[serde(rename = "magFilter")]
+  #[serde(default)]
+  pub mag_filter: Option<MagFilter>,
+  #[serde(rename = "minFilter")]
+  #[serde(default)]
+  pub min_filter: Option<MinFilter>,
+  /// Scales the normals read from a normal texture.
+  #[serde(default)]
+  pub scale: Option<f32>,
+  /// How strongly an occlusion texture is applied, from 0 to 1.
+  #[serde(default)]
+  pub strength: Option<f32>,
+}
+
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Wrap {
+  #[default]
+  Repeat,
+  ClampToEdge,
+  MirroredRepeat,
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum MagFilter {
+  Nearest,
+  Linear,
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum MinFilter {
+  Nearest,
+  Linear,
+  NearestMipmapNearest,
+  LinearMipmapNearest,
+  NearestMipmapLinear,
+  LinearMipmapLinear,
 }
 
 #[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
@@ -213,4 +824,48 @@ pub enum Target {
   Translation,
   Rotation,
   Scale,
+  /// The weights of the morph targets of the mesh of the node.
+  Weights,
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn assert_quat_eq(actual: glam::Quat, expected: [f32; 4]) {
+    let actual = [actual.x, actual.y, actual.z, actual.w];
+    assert!(actual.iter().zip(&expected).all(|(a, b)| (a - b).abs() < 1e-5),
+      "expected {:?}, found {:?}", expected, actual);
+  }
+
+  #[test]
+  fn legacy_order_matches_the_original_rotations() {
+    let angles = Vector3::new(30.0, 45.0, 60.0);
+    let expected = [0.360423, 0.43968, 0.02226, 0.822363];
+    assert_quat_eq(RotationOrder::default().to_quat(angles), expected);
+
+    let original = glam::Quat::from_euler(glam::EulerRot::ZYX, 30f32.to_radians(),
+      45f32.to_radians(), 60f32.to_radians());
+    assert_quat_eq(original, expected);
+  }
+
+  #[test]
+  fn orders_rotate_around_the_named_axes() {
+    let angles = Vector3::new(30.0, 45.0, 60.0);
+    assert_quat_eq(RotationOrder::Xyz.to_quat(angles), [0.02226, 0.43968, 0.360423, 0.822363]);
+    assert_quat_eq(RotationOrder::Xyz.to_quat(Vector3::new(90.0, 0.0, 0.0)),
+      [std::f32::consts::FRAC_1_SQRT_2, 0.0, 0.0, std::f32::consts::FRAC_1_SQRT_2]);
+
+    let x = glam::Quat::from_rotation_x(30f32.to_radians());
+    let y = glam::Quat::from_rotation_y(45f32.to_radians());
+    let z = glam::Quat::from_rotation_z(60f32.to_radians());
+    let zyx = x * y * z;
+    assert_quat_eq(RotationOrder::Zyx.to_quat(angles), [zyx.x, zyx.y, zyx.z, zyx.w]);
+  }
+
+  #[test]
+  fn euler_rotations_without_an_order_use_the_legacy_order() {
+    let rotation: Rotation = serde_yaml::from_str("{ x: 30, y: 45, z: 60 }").unwrap();
+    assert_quat_eq(rotation.to_quat(), [0.360423, 0.43968, 0.02226, 0.822363]);
+  }
 }
@@ -0,0 +1,896 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::compile::{copy_name, expand_arrays, expand_templates, image_mime_type};
+use crate::{
+  AlphaMode, ArrayPattern, Camera, DocumentPath, Error, Geometry, Interpolation, KeyframeValue,
+  Light, Model, Node, ReferenceKind, Rotation, Target, Texture, VertexColors,
+};
+
+/// Check a model for problems that would make it fail to compile or produce an invalid glTF
+/// document, returning every problem found.
+pub fn validate(model: &Model) -> Vec<Error> {
+  let mut errors = Vec::new();
+
+  if !model.include.is_empty() {
+    errors.push(Error::invalid(DocumentPath::root().key("include"),
+      "Included files are only loaded when compiling a model file"));
+  }
+
+  let material_names = check_unique_names(&mut errors, "materials", "material",
+    model.materials.iter().map(|it| it.name.as_str()));
+  let mesh_names = check_unique_names(&mut errors, "meshes", "mesh",
+    model.meshes.iter().map(|it| it.name.as_str()));
+  check_unique_names(&mut errors, "animations", "animation",
+    model.animations.iter().map(|it| it.name.as_str()));
+  check_unique_names(&mut errors, "skins", "skin", model.skins.iter().map(|it| it.name.as_str()));
+
+  for (material_index, material) in model.materials.iter().enumerate() {
+    let path = DocumentPath::root().key("materials").index(material_index);
+    let textures = [
+      ("baseColorTexture", &material.base_color_texture),
+      ("metallicRoughnessTexture", &material.metallic_roughness_texture),
+      ("normalTexture", &material.normal_texture),
+      ("occlusionTexture", &material.occlusion_texture),
+      ("emissiveTexture", &material.emissive_texture),
+    ];
+    for (key, texture) in textures {
+      if let Some(texture) = texture {
+        check_texture(&mut errors, path.key(key), key, texture);
+      }
+    }
+
+    if let Some(emissive) = material.emissive {
+      if emissive.alpha != 1.0 {
+        errors.push(Error::invalid(path.key("emissive"), "Emissive colors have no alpha"));
+      }
+    }
+
+    if let Some(cutoff) = material.alpha_cutoff {
+      if material.alpha_mode != AlphaMode::Mask {
+        errors.push(Error::invalid(path.key("alphaCutoff"),
+          "alphaCutoff is only used when alphaMode is Mask"));
+      } else if cutoff < 0.0 {
+        errors.push(Error::invalid(path.key("alphaCutoff"),
+          format!("alphaCutoff must not be negative, found {}", cutoff)));
+      }
+    }
+  }
+
+  for (mesh_index, mesh) in model.meshes.iter().enumerate() {
+    // glTF meshes need at least one primitive, and accessors at least one element.
+    if mesh.primitives.is_empty() {
+      errors.push(Error::invalid(DocumentPath::root().key("meshes").index(mesh_index)
+        .key("primitives"), format!("Mesh '{}' has no primitives", mesh.name)));
+    }
+    for (primitive_index, primitive) in mesh.primitives.iter().enumerate() {
+      let path = DocumentPath::root()
+        .key("meshes").index(mesh_index)
+        .key("primitives").index(primitive_index);
+
+      if primitive.geometry.is_empty() {
+        errors.push(Error::invalid(path.key("geometry"), "Primitive has no geometry"));
+      }
+
+      if let Some(material) = &primitive.material {
+        if !material_names.contains(material.as_str()) {
+          errors.push(Error::reference(path.key("material"), ReferenceKind::Material, material));
+        }
+      }
+
+      for (geometry_index, geometry) in primitive.geometry.iter().enumerate() {
+        check_geometry(&mut errors, path.key("geometry").index(geometry_index), geometry);
+      }
+    }
+
+    let mesh_path = DocumentPath::root().key("meshes").index(mesh_index);
+    let mut target_names = HashSet::new();
+    for (target_index, target) in mesh.targets.iter().enumerate() {
+      let path = mesh_path.key("targets").index(target_index);
+      if !target_names.insert(target.name.as_str()) {
+        errors.push(Error::invalid(path.key("name"),
+          format!("Duplicate morph target name '{}'", target.name)));
+      }
+
+      if let Some(primitives) = &target.primitives {
+        if primitives.len() != mesh.primitives.len() {
+          errors.push(Error::invalid(path.key("primitives"),
+            format!("Expected geometry for each of the {} primitives of the mesh, found {}",
+              mesh.primitives.len(), primitives.len())));
+        }
+        for (primitive_index, primitive) in primitives.iter().enumerate() {
+          for (geometry_index, geometry) in primitive.geometry.iter().enumerate() {
+            check_geometry(&mut errors, path.key("primitives").index(primitive_index)
+              .key("geometry").index(geometry_index), geometry);
+          }
+        }
+      }
+    }
+  }
+
+  check_unique_names(&mut errors, "templates", "template",
+    model.templates.iter().map(|it| it.name.as_str()));
+  let references = NodeReferences {
+    materials: material_names,
+    meshes: mesh_names,
+    templates: &model.templates,
+  };
+
+  let mut has_cycle = false;
+  for (i, template) in model.templates.iter().enumerate() {
+    let path = DocumentPath::root().key("templates").index(i);
+    // Node names only need to be unique within the template, as instances rename them.
+    check_node(&mut errors, &references, path.clone(), template);
+    if template.array.is_some() {
+      errors.push(Error::invalid(path.key("array"),
+        "Templates cannot have an array, give it to their instances instead"));
+    }
+    check_node_names(&mut errors, &mut HashMap::new(), &model.templates, path.clone(), template);
+
+    if instances_template(&model.templates, template, &template.name, &mut HashSet::new()) {
+      errors.push(Error::invalid(path.key("name"),
+        format!("Template '{}' contains an instance of itself", template.name)));
+      has_cycle = true;
+    }
+  }
+
+  let mut node_paths = HashMap::new();
+  for (i, node) in model.nodes.iter().enumerate() {
+    let path = DocumentPath::root().key("nodes").index(i);
+    check_node(&mut errors, &references, path.clone(), node);
+    check_node_names(&mut errors, &mut node_paths, &model.templates, path, node);
+  }
+
+  // The remaining checks look at the nodes copied from templates and arrays as well.
+  let expanded;
+  let model = if has_cycle {
+    model
+  } else {
+    expanded = expand_templates(&expand_arrays(model));
+    &expanded
+  };
+
+  check_skins(&mut errors, model, &node_paths);
+
+  check_unique_names(&mut errors, "scenes", "scene",
+    model.scenes.iter().map(|it| it.name.as_str()));
+  for (scene_index, scene) in model.scenes.iter().enumerate() {
+    let path = DocumentPath::root().key("scenes").index(scene_index).key("nodes");
+    let mut scene_nodes = HashSet::new();
+    for (i, name) in scene.nodes.iter().enumerate() {
+      if !node_paths.contains_key(name.as_str()) {
+        errors.push(Error::reference(path.index(i), ReferenceKind::Node, name));
+      } else if !model.nodes.iter().any(|it| it.name == *name) {
+        errors.push(Error::invalid(path.index(i),
+          format!("Node '{}' is the child of another node and cannot be a root of a scene", name)));
+      } else if !scene_nodes.insert(name.as_str()) {
+        errors.push(Error::invalid(path.index(i),
+          format!("Node '{}' is already in the scene", name)));
+      }
+    }
+  }
+  if let Some(name) = &model.default_scene {
+    if !model.scenes.iter().any(|it| it.name == *name) {
+      errors.push(Error::reference(DocumentPath::root().key("defaultScene"), ReferenceKind::Scene,
+        name));
+    }
+  }
+
+  for (animation_index, animation) in model.animations.iter().enumerate() {
+    for (channel_index, channel) in animation.channels.iter().enumerate() {
+      let path = DocumentPath::root()
+        .key("animations").index(animation_index)
+        .key("channels").index(channel_index);
+
+      for (node_index, node) in channel.nodes.iter().enumerate() {
+        if !node_paths.contains_key(node.as_str()) {
+          errors.push(Error::reference(path.key("nodes").index(node_index), ReferenceKind::Node,
+            node));
+        }
+      }
+
+      if channel.keyframes.is_empty() {
+        errors.push(Error::invalid(path.key("keyframes"), "Channel has no keyframes"));
+      }
+
+      if let Some(tangents) = &channel.tangents {
+        if channel.interpolation != Interpolation::CubicSpline {
+          errors.push(Error::invalid(path.key("tangents"),
+            "Tangents are only used by CubicSpline interpolation"));
+        } else if channel.target == Target::Rotation || channel.target == Target::Weights {
+          errors.push(Error::invalid(path.key("tangents"),
+            "Tangents of rotation and weights channels are always calculated"));
+        } else if tangents.len() != channel.keyframes.len() {
+          errors.push(Error::invalid(path.key("tangents"),
+            format!("Expected a pair of tangents for each of the {} keyframes, found {}",
+              channel.keyframes.len(), tangents.len())));
+        }
+      }
+
+      let target_count = if channel.target == Target::Weights {
+        check_weights_nodes(&mut errors, model, &path, &channel.nodes)
+      } else {
+        None
+      };
+
+      for (i, (_, value)) in channel.keyframes.iter().enumerate() {
+        let path = path.key("keyframes").index(i).index(1);
+        match *value {
+          KeyframeValue::Weights { ref weights } if channel.target == Target::Weights => {
+            if target_count.is_some_and(|it| it != weights.len()) {
+              errors.push(Error::invalid(path.key("weights"),
+                format!("Expected a weight for each of the {} morph targets, found {}",
+                  target_count.unwrap_or_default(), weights.len())));
+            }
+          }
+          _ if channel.target == Target::Weights => {
+            errors.push(Error::invalid(path,
+              "Weights keyframes must be written as { weights: [...] }"));
+          }
+          KeyframeValue::Weights { .. } => {
+            errors.push(Error::invalid(path, "Only weights channels can have weights keyframes"));
+          }
+          KeyframeValue::Vector(_) => {}
+          _ if channel.target != Target::Rotation => {
+            errors.push(Error::invalid(path,
+              "Only rotation keyframes can be quaternions or have an axis and angle"));
+          }
+          KeyframeValue::Quaternion { x, y, z, w } => {
+            check_rotation(&mut errors, path, &Rotation::Quaternion { x, y, z, w });
+          }
+          KeyframeValue::AxisAngle { axis, angle } => {
+            check_rotation(&mut errors, path, &Rotation::AxisAngle { axis, angle });
+          }
+        }
+      }
+
+      if channel.order.is_some() && channel.target != Target::Rotation {
+        errors.push(Error::invalid(path.key("order"),
+          "Only rotation channels have a rotation order"));
+      }
+
+      for (i, pair) in channel.keyframes.windows(2).enumerate() {
+        if pair[1].0 <= pair[0].0 {
+          errors.push(Error::invalid(path.key("keyframes").index(i + 1),
+            format!("Keyframe time {} is not after the previous keyframe time {}", pair[1].0,
+              pair[0].0)));
+        }
+      }
+    }
+  }
+
+  errors
+}
+
+fn check_unique_names<'a>(
+  errors: &mut Vec<Error>,
+  key: &str,
+  kind: &str,
+  names: impl Iterator<Item=&'a str>,
+) -> HashSet<&'a str> {
+  let mut seen = HashSet::new();
+  for (i, name) in names.enumerate() {
+    if !seen.insert(name) {
+      errors.push(Error::invalid(DocumentPath::root().key(key).index(i).key("name"),
+        format!("Duplicate {} name '{}'", kind, name)));
+    }
+  }
+  seen
+}
+
+fn check_skins(errors: &mut Vec<Error>, model: &Model, node_paths: &HashMap<String, DocumentPath>) {
+  for (skin_index, skin) in model.skins.iter().enumerate() {
+    let path = DocumentPath::root().key("skins").index(skin_index);
+    if skin.joints.is_empty() {
+      errors.push(Error::invalid(path.key("joints"), "Skin has no joints"));
+    }
+
+    let mut joints = HashSet::new();
+    for (i, joint) in skin.joints.iter().enumerate() {
+      if !node_paths.contains_key(joint.as_str()) {
+        errors.push(Error::reference(path.key("joints").index(i), ReferenceKind::Node, joint));
+      } else if !joints.insert(joint.as_str()) {
+        errors.push(Error::invalid(path.key("joints").index(i),
+          format!("Duplicate joint '{}'", joint)));
+      }
+    }
+
+    for (i, volume) in skin.volumes.iter().enumerate() {
+      let path = path.key("volumes").index(i);
+      if !skin.joints.contains(&volume.joint) {
+        errors.push(Error::invalid(path.key("joint"),
+          format!("'{}' is not a joint of skin '{}'", volume.joint, skin.name)));
+      }
+      if volume.min.x > volume.max.x || volume.min.y > volume.max.y || volume.min.z > volume.max.z {
+        errors.push(Error::invalid(path.key("max"), "Volume max must not be below min"));
+      }
+    }
+  }
+
+  let mut skinned_nodes = Vec::new();
+  collect_skinned_nodes(&model.nodes, DocumentPath::root().key("nodes"), &mut skinned_nodes);
+
+  let mut mesh_skins: HashMap<&str, &str> = HashMap::new();
+  for (path, node) in skinned_nodes {
+    let skin = node.skin.as_deref().unwrap_or_default();
+    if !model.skins.iter().any(|it| it.name == skin) {
+      errors.push(Error::reference(path.key("skin"), ReferenceKind::Skin, skin));
+    }
+
+    match &node.mesh {
+      None => {
+        errors.push(Error::invalid(path.key("skin"), "Only nodes with a mesh can have a skin"));
+      }
+      Some(mesh) => {
+        let previous = *mesh_skins.entry(mesh).or_insert(skin);
+        if previous != skin {
+          errors.push(Error::invalid(path.key("skin"),
+            format!("Mesh '{}' is already skinned by '{}'", mesh, previous)));
+        }
+      }
+    }
+  }
+
+  for (mesh_index, mesh) in model.meshes.iter().enumerate() {
+    let skin = mesh_skins.get(mesh.name.as_str())
+      .and_then(|name| model.skins.iter().find(|it| it.name == *name));
+    for (primitive_index, primitive) in mesh.primitives.iter().enumerate() {
+      let joint = match &primitive.joint {
+        Some(joint) => joint,
+        None => continue,
+      };
+      let path = DocumentPath::root()
+        .key("meshes").index(mesh_index)
+        .key("primitives").index(primitive_index)
+        .key("joint");
+      match skin {
+        Some(skin) if !skin.joints.contains(joint) => {
+          errors.push(Error::invalid(path,
+            format!("'{}' is not a joint of skin '{}'", joint, skin.name)));
+        }
+        Some(_) => {}
+        None if !mesh_skins.contains_key(mesh.name.as_str()) => {
+          errors.push(Error::invalid(path, "Only primitives of skinned meshes can have a joint"));
+        }
+        // The skin is unknown and reported as a reference already.
+        None => {}
+      }
+    }
+  }
+}
+
+fn collect_skinned_nodes<'a>(
+  nodes: &'a [Node],
+  path: DocumentPath,
+  skinned_nodes: &mut Vec<(DocumentPath, &'a Node)>,
+) {
+  for (i, node) in nodes.iter().enumerate() {
+    if node.skin.is_some() {
+      skinned_nodes.push((path.index(i), node));
+    }
+    collect_skinned_nodes(&node.children, path.index(i).key("children"), skinned_nodes);
+  }
+}
+
+/// Check that every node of a weights channel has a mesh with morph targets, and that the meshes
+/// have the same number of targets, returning that number.
+fn check_weights_nodes(
+  errors: &mut Vec<Error>,
+  model: &Model,
+  path: &DocumentPath,
+  nodes: &[String],
+) -> Option<usize> {
+  let mut target_count = None;
+  for (i, name) in nodes.iter().enumerate() {
+    let path = path.key("nodes").index(i);
+    // Unknown nodes and meshes are reported as references already.
+    let node = match find_node(&model.nodes, name) {
+      Some(node) => node,
+      None => continue,
+    };
+    let mesh = node.mesh.as_ref().map(|mesh| model.meshes.iter().find(|it| it.name == *mesh));
+    let count = match mesh {
+      Some(None) => continue,
+      Some(Some(mesh)) if !mesh.targets.is_empty() => mesh.targets.len(),
+      _ => {
+        errors.push(Error::invalid(path,
+          format!("Node '{}' has no morph targets to animate", name)));
+        continue;
+      }
+    };
+
+    match target_count {
+      Some(previous) if previous != count => {
+        errors.push(Error::invalid(path, format!("Node '{}' has {} morph targets, but the nodes \
+          before it have {}", name, count, previous)));
+      }
+      _ => target_count = Some(count),
+    }
+  }
+  target_count
+}
+
+fn find_node<'a>(nodes: &'a [Node], name: &str) -> Option<&'a Node> {
+  nodes.iter().find_map(|node| {
+    if node.name == name {
+      Some(node)
+    } else {
+      find_node(&node.children, name)
+    }
+  })
+}
+
+fn check_rotation(errors: &mut Vec<Error>, path: DocumentPath, rotation: &Rotation) {
+  match *rotation {
+    Rotation::Quaternion { x, y, z, w } => {
+      if x == 0.0 && y == 0.0 && z == 0.0 && w == 0.0 {
+        errors.push(Error::invalid(path, "Quaternion must not be zero"));
+      }
+    }
+    Rotation::AxisAngle { axis, .. } => {
+      if axis.dot(axis) == 0.0 {
+        errors.push(Error::invalid(path.key("axis"), "Rotation axis must not be zero"));
+      }
+    }
+    Rotation::Euler { .. } => {}
+  }
+}
+
+fn check_texture(errors: &mut Vec<Error>, path: DocumentPath, key: &str, texture: &Texture) {
+  if image_mime_type(&texture.image).is_none() {
+    errors.push(Error::invalid(path.key("image"),
+      format!("Image '{}' is not a PNG or JPEG file", texture.image)));
+  }
+  if texture.scale.is_some() && key != "normalTexture" {
+    errors.push(Error::invalid(path.key("scale"), "Only a normalTexture can have a scale"));
+  }
+  if texture.strength.is_some() && key != "occlusionTexture" {
+    errors.push(Error::invalid(path.key("strength"),
+      "Only an occlusionTexture can have a strength"));
+  }
+}
+
+/// The names that nodes can refer to.
+struct NodeReferences<'a> {
+  materials: HashSet<&'a str>,
+  meshes: HashSet<&'a str>,
+  templates: &'a [Node],
+}
+
+fn check_node(
+  errors: &mut Vec<Error>,
+  references: &NodeReferences,
+  path: DocumentPath,
+  node: &Node,
+) {
+  if let Some(template) = &node.template {
+    if !references.templates.iter().any(|it| it.name == *template) {
+      errors.push(Error::reference(path.key("template"), ReferenceKind::Template, template));
+    }
+  }
+  if let Some(material) = &node.material {
+    if !references.materials.contains(material.as_str()) {
+      errors.push(Error::reference(path.key("material"), ReferenceKind::Material, material));
+    }
+  }
+
+  if let Some(rotation) = &node.rotation {
+    check_rotation(errors, path.key("rotation"), rotation);
+  }
+  if let Some(camera) = &node.camera {
+    check_camera(errors, path.key("camera"), camera);
+  }
+  if let Some(light) = &node.light {
+    check_light(errors, path.key("light"), light);
+  }
+  if let Some(array) = &node.array {
+    check_array(errors, path.key("array"), array);
+  }
+
+  if let Some(mesh) = &node.mesh {
+    if !references.meshes.contains(mesh.as_str()) {
+      errors.push(Error::reference(path.key("mesh"), ReferenceKind::Mesh, mesh));
+    }
+  }
+
+  for (i, child) in node.children.iter().enumerate() {
+    check_node(errors, references, path.key("children").index(i), child);
+  }
+}
+
+/// Check that no two nodes share a name, once templates and arrays are expanded, and record
+/// where each name comes from.
+fn check_node_names(
+  errors: &mut Vec<Error>,
+  node_paths: &mut HashMap<String, DocumentPath>,
+  templates: &[Node],
+  path: DocumentPath,
+  node: &Node,
+) {
+  for (name, path) in expanded_names(templates, path, node, &mut Vec::new()) {
+    if let Some(previous) = node_paths.get(&name) {
+      errors.push(Error::invalid(path.key("name"),
+        format!("Duplicate node name '{}', first used at {}", name, previous)));
+    } else {
+      node_paths.insert(name, path);
+    }
+  }
+}
+
+/// The names of a node and its descendants once arrays and templates are expanded, the same way
+/// `expand_arrays` and `expand_templates` name them. A node copied from a template has the path of
+/// the instance.
+fn expanded_names<'a>(
+  templates: &'a [Node],
+  path: DocumentPath,
+  node: &'a Node,
+  visiting: &mut Vec<&'a str>,
+) -> Vec<(String, DocumentPath)> {
+  let mut descendants = Vec::new();
+  for (i, child) in node.children.iter().enumerate() {
+    descendants.extend(expanded_names(templates, path.key("children").index(i), child, visiting));
+  }
+
+  let copies = match &node.array {
+    Some(pattern) => (0..pattern.count())
+      .map(|i| {
+        let copy = copy_name(&node.name, i);
+        let descendants = descendants.iter()
+          .map(|(name, path)| {
+            let name = if name.contains("{i}") {
+              name.replace("{i}", &i.to_string())
+            } else {
+              format!("{}/{}", copy, name)
+            };
+            (name, path.clone())
+          })
+          .collect();
+        (copy, descendants)
+      })
+      .collect(),
+    None => vec![(node.name.clone(), descendants)],
+  };
+
+  // Unknown templates and cycles are reported where they are used.
+  let template = node.template.as_ref()
+    .and_then(|name| templates.iter().find(|it| it.name == *name))
+    .filter(|it| !visiting.contains(&it.name.as_str()));
+  let copied = match template {
+    Some(template) => {
+      visiting.push(&template.name);
+      let copied = expanded_names(templates, path.clone(), template, visiting);
+      visiting.pop();
+      copied.into_iter().skip(1).map(|(name, _)| name).collect()
+    }
+    None => Vec::new(),
+  };
+
+  let mut names = Vec::new();
+  for (copy, descendants) in copies {
+    names.push((copy.clone(), path.clone()));
+    names.extend(descendants);
+    names.extend(copied.iter().map(|name| (format!("{}/{}", copy, name), path.clone())));
+  }
+  names
+}
+
+/// Whether a node or any of its descendants instances the template `name`, directly or through
+/// other templates.
+fn instances_template<'a>(
+  templates: &'a [Node],
+  node: &'a Node,
+  name: &str,
+  visited: &mut HashSet<&'a str>,
+) -> bool {
+  if let Some(template) = &node.template {
+    if template == name {
+      return true;
+    }
+    if visited.insert(template) {
+      let found = templates.iter().find(|it| it.name == *template)
+        .is_some_and(|it| instances_template(templates, it, name, visited));
+      if found {
+        return true;
+      }
+    }
+  }
+  node.children.iter().any(|it| instances_template(templates, it, name, visited))
+}
+
+fn check_camera(errors: &mut Vec<Error>, path: DocumentPath, camera: &Camera) {
+  let (path, znear, zfar) = match camera {
+    Camera::Perspective(it) => {
+      let path = path.key("Perspective");
+      if it.yfov <= 0.0 || it.yfov >= 180.0 {
+        errors.push(Error::invalid(path.key("yfov"),
+          format!("Field of view must be between 0 and 180 degrees, found {}", it.yfov)));
+      }
+      if it.aspect_ratio.is_some_and(|it| it <= 0.0) {
+        errors.push(Error::invalid(path.key("aspectRatio"), "Aspect ratio must be positive"));
+      }
+      if it.znear <= 0.0 {
+        errors.push(Error::invalid(path.key("znear"),
+          "The near clipping distance of a perspective camera must be positive"));
+      }
+      (path, it.znear, it.zfar)
+    }
+    Camera::Orthographic(it) => {
+      let path = path.key("Orthographic");
+      if it.xmag == 0.0 || it.ymag == 0.0 {
+        errors.push(Error::invalid(path.clone(), "xmag and ymag must not be zero"));
+      }
+      if it.znear < 0.0 {
+        errors.push(Error::invalid(path.key("znear"),
+          "The near clipping distance must not be negative"));
+      }
+      (path, it.znear, Some(it.zfar))
+    }
+  };
+
+  if let Some(zfar) = zfar {
+    if zfar <= znear {
+      errors.push(Error::invalid(path.key("zfar"),
+        format!("The far clipping distance {} must be beyond the near distance {}", zfar, znear)));
+    }
+  }
+}
+
+fn check_light(errors: &mut Vec<Error>, path: DocumentPath, light: &Light) {
+  let (path, color, intensity, range) = match light {
+    Light::Point(it) => (path.key("Point"), it.color, it.intensity, it.range),
+    Light::Spot(it) => (path.key("Spot"), it.color, it.intensity, it.range),
+    Light::Directional(it) => (path.key("Directional"), it.color, it.intensity, None),
+  };
+
+  if color.alpha != 1.0 {
+    errors.push(Error::invalid(path.key("color"), "Light colors have no alpha"));
+  }
+  if intensity < 0.0 {
+    errors.push(Error::invalid(path.key("intensity"), "Light intensity must not be negative"));
+  }
+  if range.is_some_and(|it| it <= 0.0) {
+    errors.push(Error::invalid(path.key("range"), "Light range must be positive"));
+  }
+
+  if let Light::Spot(spot) = light {
+    if spot.outer_cone_angle <= 0.0 || spot.outer_cone_angle > 90.0 {
+      errors.push(Error::invalid(path.key("outerConeAngle"),
+        format!("Outer cone angle must be above 0 and at most 90 degrees, found {}",
+          spot.outer_cone_angle)));
+    }
+    if spot.inner_cone_angle < 0.0 || spot.inner_cone_angle >= spot.outer_cone_angle {
+      errors.push(Error::invalid(path.key("innerConeAngle"),
+        format!("Inner cone angle must be at least 0 and below the outer cone angle, found {}",
+          spot.inner_cone_angle)));
+    }
+  }
+}
+
+fn check_array(errors: &mut Vec<Error>, path: DocumentPath, pattern: &ArrayPattern) {
+  match pattern {
+    ArrayPattern::Linear { count, rotation, .. } => {
+      if *count == 0 {
+        errors.push(Error::invalid(path.key("Linear").key("count"),
+          "Linear array needs at least 1 copy"));
+      }
+      if let Some(rotation) = rotation {
+        check_rotation(errors, path.key("Linear").key("rotation"), rotation);
+      }
+    }
+    ArrayPattern::Grid { count, .. } => {
+      if count.x == 0 || count.y == 0 || count.z == 0 {
+        errors.push(Error::invalid(path.key("Grid").key("count"),
+          format!("Grid array needs at least 1 copy along every axis, found {}, {} and {}",
+            count.x, count.y, count.z)));
+      }
+    }
+    ArrayPattern::Radial { count, .. } => {
+      if *count == 0 {
+        errors.push(Error::invalid(path.key("Radial").key("count"),
+          "Radial array needs at least 1 copy"));
+      }
+    }
+  }
+}
+
+fn check_geometry(errors: &mut Vec<Error>, path: DocumentPath, geometry: &Geometry) {
+  match geometry {
+    Geometry::Cone(cone) => {
+      if cone.divides < 2 {
+        errors.push(Error::invalid(path.key("Cone").key("divides"),
+          format!("Cone needs at least 2 divides, found {}", cone.divides)));
+      }
+    }
+    Geometry::Cube(cube) => {
+      if cube.size.x == 0.0 || cube.size.y == 0.0 || cube.size.z == 0.0 {
+        errors.push(Error::invalid(path.key("Cube").key("size"),
+          "Cube size must be non-zero on every axis"));
+      }
+    }
+    Geometry::Cylinder(cylinder) => {
+      if cylinder.points < 3 {
+        errors.push(Error::invalid(path.key("Cylinder").key("points"),
+          format!("Cylinder needs at least 3 points, found {}", cylinder.points)));
+      }
+    }
+    Geometry::Array(array) => {
+      check_array(errors, path.key("Array").key("pattern"), &array.pattern);
+      check_geometry(errors, path.key("Array").key("geometry"), &array.geometry);
+    }
+    Geometry::Deform(deform) => {
+      check_geometry(errors, path.key("Deform").key("geometry"), &deform.geometry);
+    }
+    Geometry::UvSphere(sphere) => {
+      if sphere.u < 2 || sphere.v < 2 {
+        errors.push(Error::invalid(path.key("UvSphere"),
+          format!("UvSphere needs at least 2 divisions in u and v, found {} and {}", sphere.u,
+            sphere.v)));
+      }
+    }
+    Geometry::Icosphere(_) | Geometry::Plane(_) | Geometry::Triangle(_) => {}
+  }
+
+  let (kind, colors, rotation) = match geometry {
+    Geometry::Cone(it) => ("Cone", &it.colors, &it.rotation),
+    Geometry::Cube(it) => ("Cube", &it.colors, &it.rotation),
+    Geometry::Cylinder(it) => ("Cylinder", &it.colors, &it.rotation),
+    Geometry::Icosphere(it) => ("Icosphere", &it.colors, &it.rotation),
+    Geometry::Plane(it) => ("Plane", &it.colors, &it.rotation),
+    Geometry::Triangle(it) => ("Triangle", &it.colors, &it.rotation),
+    Geometry::UvSphere(it) => ("UvSphere", &it.colors, &it.rotation),
+    Geometry::Array(_) | Geometry::Deform(_) => return,
+  };
+  if let Some(rotation) = rotation {
+    check_rotation(errors, path.key(kind).key("rotation"), rotation);
+  }
+  if let Some(VertexColors::Faces(colors)) = colors {
+    if colors.is_empty() {
+      errors.push(Error::invalid(path.key(kind).key("colors").key("Faces"),
+        "Faces needs at least one color"));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn errors(src: &str) -> Vec<String> {
+    validate(&crate::parse(src).unwrap()).iter().map(|it| it.to_string()).collect()
+  }
+
+  fn geometry_errors(geometry: &str) -> Vec<String> {
+    errors(&format!("meshes: [{{ name: a, primitives: [{{ geometry: [{}] }}] }}]", geometry))
+  }
+
+  const TRIANGLE: &str = "
+meshes:
+  - name: triangle
+    primitives: [{ geometry: [Triangle: { points: [{ x: -1 }, { x: 1 }, { y: 1 }] }] }]
+nodes:
+  - name: triangle
+    mesh: triangle
+";
+
+  #[test]
+  fn valid_models_have_no_errors() {
+    assert!(errors(TRIANGLE).is_empty());
+    assert!(geometry_errors("Cone: {}").is_empty());
+  }
+
+  #[test]
+  fn names_must_be_unique() {
+    let src = format!("{}
+  - name: triangle
+    mesh: triangle
+materials: [{{ name: red }}, {{ name: red }}]
+", TRIANGLE);
+    assert_eq!(errors(&src), vec![
+      "materials[1].name: Duplicate material name 'red'",
+      "nodes[1].name: Duplicate node name 'triangle', first used at nodes[0]",
+    ]);
+  }
+
+  #[test]
+  fn references_must_exist() {
+    let src = format!("{}
+  - name: other
+    mesh: square
+    material: red
+    template: corner
+scenes: [{{ name: main, nodes: [triangle, missing] }}]
+animations:
+  - name: move
+    channels: [{{ nodes: [gone], target: Translation, keyframes: [[0, {{ x: 0 }}]] }}]
+", TRIANGLE);
+    assert_eq!(errors(&src), vec![
+      "nodes[1].template: Cannot find template 'corner'",
+      "nodes[1].material: Cannot find material 'red'",
+      "nodes[1].mesh: Cannot find mesh 'square'",
+      "scenes[0].nodes[1]: Cannot find node 'missing'",
+      "animations[0].channels[0].nodes[0]: Cannot find node 'gone'",
+    ]);
+  }
+
+  #[test]
+  fn templates_cannot_instance_themselves() {
+    let src = "
+templates:
+  - name: a
+    children: [{ name: b, template: b }]
+  - name: b
+    template: a
+  - name: c
+    template: a
+";
+    assert_eq!(errors(src), vec![
+      "templates[0].name: Template 'a' contains an instance of itself",
+      "templates[1].name: Template 'b' contains an instance of itself",
+    ]);
+  }
+
+  #[test]
+  fn keyframes_must_exist_and_increase() {
+    let src = format!("{}
+animations:
+  - name: move
+    channels:
+      - {{ nodes: [triangle], target: Translation, keyframes: [] }}
+      - nodes: [triangle]
+        target: Scale
+        keyframes: [[0, {{ x: 1 }}], [1, {{ x: 2 }}], [1, {{ x: 3 }}], [0.5, {{ x: 4 }}]]
+", TRIANGLE);
+    assert_eq!(errors(&src), vec![
+      "animations[0].channels[0].keyframes: Channel has no keyframes",
+      "animations[0].channels[1].keyframes[2]: Keyframe time 1 is not after the previous \
+        keyframe time 1",
+      "animations[0].channels[1].keyframes[3]: Keyframe time 0.5 is not after the previous \
+        keyframe time 1",
+    ]);
+  }
+
+  #[test]
+  fn rotation_channels_cannot_have_tangents() {
+    let src = format!("{}
+animations:
+  - name: turn
+    channels:
+      - nodes: [triangle]
+        target: Rotation
+        interpolation: CubicSpline
+        keyframes: [[0, {{ y: 0 }}], [1, {{ y: 90 }}]]
+        tangents: [[{{}}, {{}}], [{{}}, {{}}]]
+", TRIANGLE);
+    assert_eq!(errors(&src), vec![
+      "animations[0].channels[0].tangents: Tangents of rotation and weights channels are always \
+        calculated",
+    ]);
+  }
+
+  #[test]
+  fn geometry_must_have_enough_size_and_divisions() {
+    assert_eq!(geometry_errors("Cube: { size: { x: 1, y: 1 } }"), vec![
+      "meshes[0].primitives[0].geometry[0].Cube.size: Cube size must be non-zero on every axis",
+    ]);
+    assert_eq!(geometry_errors("Cylinder: { points: 2 }"), vec![
+      "meshes[0].primitives[0].geometry[0].Cylinder.points: Cylinder needs at least 3 points, \
+        found 2",
+    ]);
+    assert_eq!(geometry_errors("UvSphere: { u: 8, v: 1 }"), vec![
+      "meshes[0].primitives[0].geometry[0].UvSphere: UvSphere needs at least 2 divisions in u and \
+        v, found 8 and 1",
+    ]);
+    assert_eq!(geometry_errors("Cone: { divides: 1 }"), vec![
+      "meshes[0].primitives[0].geometry[0].Cone.divides: Cone needs at least 2 divides, found 1",
+    ]);
+  }
+
+  #[test]
+  fn meshes_need_primitives_with_geometry() {
+    assert_eq!(errors("meshes: [{ name: a, primitives: [] }]"), vec![
+      "meshes[0].primitives: Mesh 'a' has no primitives",
+    ]);
+    assert_eq!(errors("meshes: [{ name: a, primitives: [{ geometry: [] }] }]"), vec![
+      "meshes[0].primitives[0].geometry: Primitive has no geometry",
+    ]);
+  }
+}